@@ -26,12 +26,12 @@ extern crate rand;
 extern crate jubjub;
 
 use bellman::groth16::*;
-use pairing::*;
 use pairing::bls12_381::{Fr, Bls12};
 use bellman::*;
-use rand::{Rng, thread_rng, XorShiftRng, SeedableRng};
+use rand::{Rng, ThreadRng, thread_rng, XorShiftRng, SeedableRng};
 
 use jubjub::*;
+use jubjub::input::PublicInputs;
 
 struct DemoPedersenHashCircuit<'a> {
     bits: Vec<Assignment<bool>>,
@@ -64,17 +64,8 @@ impl<'a> DemoPedersenHashCircuit<'a> {
     }
 }
 
-struct DemoPedersenHashCircuitInput;
-
-impl<E: Engine> Input<E> for DemoPedersenHashCircuitInput {
-    fn synthesize<CS: PublicConstraintSystem<E>>(self, _: &mut CS) -> Result<(), Error>
-    {
-        Ok(())
-    }
-}
-
 impl<'a> Circuit<Bls12> for DemoPedersenHashCircuit<'a> {
-    type InputMap = DemoPedersenHashCircuitInput;
+    type InputMap = PublicInputs<Bls12>;
 
     fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
     {
@@ -85,6 +76,8 @@ impl<'a> Circuit<Bls12> for DemoPedersenHashCircuit<'a> {
 
         const DEPTH: usize = 100;
 
+        let mut image = None;
+
         for i in 0..DEPTH {
             let num = pedersen_hash(cs, &bits, self.generators, self.j)?;
 
@@ -99,14 +92,43 @@ impl<'a> Circuit<Bls12> for DemoPedersenHashCircuit<'a> {
 
                 assert_eq!(bits.len(), 512);
             }
+
+            image = Some(num);
         }
 
-        Ok(DemoPedersenHashCircuitInput)
+        Ok(PublicInputs::new().num(&image.unwrap()))
     }
 }
 
 use std::fs::File;
 use std::path::Path;
+use std::time::{Duration, Instant};
+
+const SAMPLES: u32 = 50;
+
+/// Creates `SAMPLES` proofs of the circuits `circuit` returns,
+/// against parameters generated for `blank`, and prints the time
+/// spent. Only proving is timed, so the figures for different
+/// hashes can be compared.
+fn bench<C, F>(name: &str, blank: C, rng: &mut ThreadRng, mut circuit: F)
+    where C: Circuit<Bls12>, F: FnMut(&mut ThreadRng) -> C
+{
+    println!("{}: Creating the parameters.", name);
+    let params = generate_random_parameters::<Bls12, _, _>(blank, rng).unwrap();
+
+    println!("{}: Creating {} proofs and averaging the time spent creating them.", name, SAMPLES);
+
+    let mut total = Duration::new(0, 0);
+    for _ in 0..SAMPLES {
+        let c = circuit(rng);
+        let now = Instant::now();
+        create_random_proof::<Bls12, _, _, _>(c, &params, rng).unwrap();
+        total += now.elapsed();
+    }
+
+    println!("{}: Total proving time: {:?}", name, total);
+    println!("{}: Average proving time: {:?}", name, total / SAMPLES);
+}
 
 fn main() {
     let rng = &mut thread_rng();
@@ -124,33 +146,38 @@ fn main() {
         println!("Just wrote the parameters to disk! We don't need to do it next time.");
     }
 
-    use std::time::{Duration,Instant};
-
-    let mut total_mimc = Duration::new(0, 0);
-
-    const SAMPLES: u32 = 50;
-
-    let constants = (0..MIMC_ROUNDS).map(|_| rng.gen()).collect::<Vec<Fr>>();
-
-    println!("MiMC: Creating {} proofs and averaging the time spent creating them.", SAMPLES);
-
-    for _ in 0..SAMPLES {
-        let xl: Fr = rng.gen();
-        let xr: Fr = rng.gen();
-        let now = Instant::now();
-        let params = ProverStream::new("params").unwrap();
-        let c = MiMCDemo::<Bls12> {
-            xl: Some(xl),
-            xr: Some(xr),
-            constants: &constants
-        };
-        create_random_proof::<Bls12, _, _, _>(c, params, rng).unwrap();
-        total_mimc += now.elapsed();
-    }
-
-    let avg_mimc = total_mimc / SAMPLES;
-    println!("MiMC: Total proving time: {:?}", total_mimc);
-    println!("MiMC: Average proving time: {:?}", avg_mimc);
+    let mimc_params = MimcParams::default();
+    bench("MiMC", MiMCDemo::<Bls12> {
+        xl: None,
+        xr: None,
+        params: &mimc_params
+    }, rng, |rng| MiMCDemo {
+        xl: Some(rng.gen()),
+        xr: Some(rng.gen()),
+        params: &mimc_params
+    });
+
+    let poseidon_params = poseidon::PoseidonParams::default();
+    bench("Poseidon", poseidon::PoseidonDemo {
+        xl: None,
+        xr: None,
+        params: &poseidon_params
+    }, rng, |rng| poseidon::PoseidonDemo {
+        xl: Some(rng.gen()),
+        xr: Some(rng.gen()),
+        params: &poseidon_params
+    });
+
+    let rescue_params = rescue::RescueParams::default();
+    bench("Rescue", rescue::RescueDemo {
+        xl: None,
+        xr: None,
+        params: &rescue_params
+    }, rng, |rng| rescue::RescueDemo {
+        xl: Some(rng.gen()),
+        xr: Some(rng.gen()),
+        params: &rescue_params
+    });
 
     println!("Pedersen: Creating {} proofs and averaging the time spent creating them.", SAMPLES);
 
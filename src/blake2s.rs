@@ -0,0 +1,424 @@
+//! The BLAKE2s hash function, with a 32-byte digest and no key,
+//! as specified in RFC 7693, natively and as a gadget.
+//!
+//! The gadget works on bits: each byte is eight booleans, least
+//! significant first, so that words are read little-endian just
+//! by concatenating bytes.
+
+use pairing::*;
+use bellman::*;
+
+use super::Boolean;
+use uint32::UInt32;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
+    0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0]
+];
+
+/// The mixing function, on words `a`, `b`, `c` and `d` of the
+/// working vector.
+fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+/// Compresses a 64-byte block into the state, where `t` is the
+/// number of bytes hashed so far.
+fn compress(h: &mut [u32; 8], block: &[u8], t: u64, last: bool) {
+    let mut m = [0u32; 16];
+    for (i, word) in m.iter_mut().enumerate() {
+        let bytes = &block[i * 4..i * 4 + 4];
+        *word = (bytes[0] as u32) |
+                ((bytes[1] as u32) << 8) |
+                ((bytes[2] as u32) << 16) |
+                ((bytes[3] as u32) << 24);
+    }
+
+    let mut v = [0u32; 16];
+    v[0..8].copy_from_slice(h);
+    v[8..16].copy_from_slice(&IV);
+    v[12] ^= t as u32;
+    v[13] ^= (t >> 32) as u32;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for s in SIGMA.iter() {
+        g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// Hashes `input` to a 32-byte digest.
+pub fn blake2s(input: &[u8]) -> [u8; 32] {
+    blake2s_personalized(input, &[0; 8])
+}
+
+/// The initial state: the IV, with the parameter block for a
+/// 32-byte digest, no key, a fanout and depth of one, and the
+/// given personalization.
+fn initial_state(personalization: &[u8; 8]) -> [u32; 8] {
+    let word = |b: &[u8]| {
+        (b[0] as u32) | ((b[1] as u32) << 8) | ((b[2] as u32) << 16) | ((b[3] as u32) << 24)
+    };
+
+    let mut h = IV;
+    h[0] ^= 0x01010000 ^ 32;
+    h[6] ^= word(&personalization[0..4]);
+    h[7] ^= word(&personalization[4..8]);
+
+    h
+}
+
+/// Hashes `input` to a 32-byte digest, with an 8-byte
+/// personalization to separate domains.
+pub fn blake2s_personalized(input: &[u8], personalization: &[u8; 8]) -> [u8; 32] {
+    let mut h = initial_state(personalization);
+
+    // The last block is compressed with the final flag set, even
+    // when it is full or the input is empty.
+    let num_blocks = if input.is_empty() { 1 } else { (input.len() + 63) / 64 };
+
+    for i in 0..num_blocks {
+        let start = i * 64;
+        let end = ::std::cmp::min(start + 64, input.len());
+
+        let mut block = [0u8; 64];
+        block[0..end - start].copy_from_slice(&input[start..end]);
+
+        compress(&mut h, &block, end as u64, i == num_blocks - 1);
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, word) in h.iter().enumerate() {
+        for j in 0..4 {
+            digest[i * 4 + j] = (word >> (j * 8)) as u8;
+        }
+    }
+
+    digest
+}
+
+/// The same as `g`, in a circuit.
+fn g_gadget<E, CS>(
+    cs: &mut CS,
+    v: &mut [UInt32],
+    (a, b, c, d): (usize, usize, usize, usize),
+    x: &UInt32,
+    y: &UInt32
+) -> Result<(), Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    v[a] = UInt32::addmany(cs, &[v[a].clone(), v[b].clone(), x.clone()])?;
+    v[d] = v[d].xor(cs, &v[a])?.rotr(16);
+    v[c] = UInt32::addmany(cs, &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs, &v[c])?.rotr(12);
+    v[a] = UInt32::addmany(cs, &[v[a].clone(), v[b].clone(), y.clone()])?;
+    v[d] = v[d].xor(cs, &v[a])?.rotr(8);
+    v[c] = UInt32::addmany(cs, &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs, &v[c])?.rotr(7);
+
+    Ok(())
+}
+
+/// The same as `compress`, in a circuit, where `block` is 512
+/// bits. Costs 21,664 constraints when `h` and `block` are
+/// witnessed. Each of the 80 G functions costs 266: two
+/// three-operand additions of 35, two two-operand additions of
+/// 34 and four xors of 32. The first four skip an xor, against
+/// constant words of the IV, saving 128, and the final xors into
+/// `h` cost 512. Constant words of `h` or `block` make it
+/// cheaper, since xors with them are free.
+fn compress_gadget<E, CS>(
+    cs: &mut CS,
+    h: &mut [UInt32],
+    block: &[Boolean],
+    t: u64,
+    last: bool
+) -> Result<(), Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(h.len(), 8);
+    assert_eq!(block.len(), 512);
+
+    let m = block.chunks(32).map(UInt32::from_bits_le).collect::<Vec<_>>();
+
+    let mut v = h.to_vec();
+    v.extend(IV.iter().map(|&w| UInt32::constant(w)));
+
+    // Xoring with constants costs no constraints.
+    v[12] = v[12].xor(cs, &UInt32::constant(t as u32))?;
+    v[13] = v[13].xor(cs, &UInt32::constant((t >> 32) as u32))?;
+    if last {
+        v[14] = v[14].xor(cs, &UInt32::constant(u32::max_value()))?;
+    }
+
+    for s in SIGMA.iter() {
+        g_gadget(cs, &mut v, (0, 4, 8, 12), &m[s[0]], &m[s[1]])?;
+        g_gadget(cs, &mut v, (1, 5, 9, 13), &m[s[2]], &m[s[3]])?;
+        g_gadget(cs, &mut v, (2, 6, 10, 14), &m[s[4]], &m[s[5]])?;
+        g_gadget(cs, &mut v, (3, 7, 11, 15), &m[s[6]], &m[s[7]])?;
+        g_gadget(cs, &mut v, (0, 5, 10, 15), &m[s[8]], &m[s[9]])?;
+        g_gadget(cs, &mut v, (1, 6, 11, 12), &m[s[10]], &m[s[11]])?;
+        g_gadget(cs, &mut v, (2, 7, 8, 13), &m[s[12]], &m[s[13]])?;
+        g_gadget(cs, &mut v, (3, 4, 9, 14), &m[s[14]], &m[s[15]])?;
+    }
+
+    for i in 0..8 {
+        h[i] = h[i].xor(cs, &v[i])?.xor(cs, &v[i + 8])?;
+    }
+
+    Ok(())
+}
+
+/// The same as `blake2s_personalized`, in a circuit. `input` is
+/// a whole number of bytes, and so is the 256-bit digest.
+pub fn blake2s_gadget<E, CS>(
+    cs: &mut CS,
+    input: &[Boolean],
+    personalization: &[u8; 8]
+) -> Result<Vec<Boolean>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len() % 8, 0);
+
+    let mut h = initial_state(personalization).iter().map(|&w| UInt32::constant(w)).collect::<Vec<_>>();
+
+    let num_blocks = if input.is_empty() { 1 } else { (input.len() + 511) / 512 };
+
+    for i in 0..num_blocks {
+        let start = i * 512;
+        let end = ::std::cmp::min(start + 512, input.len());
+
+        let mut block = input[start..end].to_vec();
+        block.resize(512, Boolean::Constant(false));
+
+        compress_gadget(cs, &mut h, &block, (end / 8) as u64, i == num_blocks - 1)?;
+    }
+
+    Ok(h.into_iter().flat_map(|w| w.into_bits_le()).collect())
+}
+
+/// Derives the `i`th field element for `domain`: the digest of
+/// `domain` followed by `i` as four little-endian bytes, read as
+/// a little-endian integer and reduced into the field. Used to
+/// generate round constants nobody chose.
+pub fn hash_to_field<E: Engine>(domain: &[u8], i: u32) -> E::Fr {
+    let small = |n: u64| E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(n)).unwrap();
+
+    let mut preimage = domain.to_vec();
+    for j in 0..4 {
+        preimage.push((i >> (j * 8)) as u8);
+    }
+
+    blake2s(&preimage).iter().rev().fold(E::Fr::zero(), |mut acc, &b| {
+        acc.mul_assign(&small(256));
+        acc.add_assign(&small(b as u64));
+        acc
+    })
+}
+
+#[test]
+fn test_blake2s() {
+    fn hex(digest: [u8; 32]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // From RFC 7693, appendix B.
+    assert_eq!(hex(blake2s(b"abc")), "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982");
+
+    assert_eq!(hex(blake2s(b"")), "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9");
+
+    // Exactly one block, and one block and a byte.
+    assert_eq!(hex(blake2s(&[0u8; 64])), "ae09db7cd54f42b490ef09b6bc541af688e4959bb8c53f359a6f56e38ab454a3");
+    assert_eq!(hex(blake2s(&[0u8; 65])), "857328bf990b00922782d3e81c6054c25d3375d386c7424abe3e01d79041046c");
+}
+
+#[test]
+fn test_blake2s_personalized() {
+    fn hex(digest: [u8; 32]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    assert_eq!(hex(blake2s_personalized(b"abc", b"Zcash_ak")), "429b1056c21b1a698856411a17452b96c197f4af4d5c1985a7144ef579262d33");
+
+    let input = (0..65).collect::<Vec<u8>>();
+    assert_eq!(hex(blake2s_personalized(&input, b"12345678")), "4804a839f3d294a1af56175267cceafe0669f7bccfeb4d99226d93df7a7e91c9");
+}
+
+#[test]
+fn test_blake2s_gadget() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::Bls12;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use multipack;
+    use super::{Assignment, Bit, ConstraintCounter};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+
+    fn bits_of(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1)).collect()
+    }
+
+    struct MyBlake2sCircuit<'a> {
+        input: Vec<Assignment<bool>>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyBlake2sCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut input = vec![];
+            for b in self.input {
+                input.push(Boolean::from_bit(Bit::alloc(cs, b)?));
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let digest = blake2s_gadget(&mut counter, &input, b"12345678")?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().packed_booleans::<CS>(&digest))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    for &(len, expected_constraints) in &[(1, 20348), (65, 42944)] {
+        let (params, ivk) = input::generate_input_parameters(MyBlake2sCircuit {
+            input: vec![Assignment::unknown(); len * 8],
+            num_constraints: &num_constraints
+        }, rng).unwrap();
+
+        assert_eq!(num_constraints.get(), expected_constraints);
+
+        let data = (0..len).map(|_| rng.gen()).collect::<Vec<u8>>();
+        let digest = blake2s_personalized(&data, b"12345678");
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyBlake2sCircuit {
+            input: bits_of(&data).into_iter().map(Assignment::known).collect(),
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        let expected = multipack::compute_multipacking::<Bls12>(&bits_of(&digest));
+        assert!(input::verify_with_inputs(&ivk, &proof, &expected).unwrap());
+
+        // Without the personalization the digest is different.
+        let unpersonalized = multipack::compute_multipacking::<Bls12>(&bits_of(&blake2s(&data)));
+        assert!(!input::verify_with_inputs(&ivk, &proof, &unpersonalized).unwrap());
+    }
+}
+
+
+#[test]
+fn test_compress_gadget() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::Bls12;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use multipack;
+    use super::{Assignment, Bit, ConstraintCounter};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+
+    fn bits_of(words: &[u32]) -> Vec<bool> {
+        words.iter().flat_map(|&w| (0..32).map(move |i| (w >> i) & 1 == 1)).collect()
+    }
+
+    struct MyCompressCircuit<'a> {
+        h: Vec<Assignment<u32>>,
+        block: Vec<Assignment<bool>>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyCompressCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut h = vec![];
+            for w in self.h {
+                h.push(UInt32::alloc(cs, w)?);
+            }
+
+            let mut block = vec![];
+            for b in self.block {
+                block.push(Boolean::from_bit(Bit::alloc(cs, b)?));
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            compress_gadget(&mut counter, &mut h, &block, 64, true)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            let bits = h.into_iter().flat_map(|w| w.into_bits_le()).collect::<Vec<_>>();
+
+            Ok(PublicInputs::new().packed_booleans::<CS>(&bits))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let (params, ivk) = input::generate_input_parameters(MyCompressCircuit {
+        h: vec![Assignment::unknown(); 8],
+        block: vec![Assignment::unknown(); 512],
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+
+    assert_eq!(num_constraints.get(), 80 * 266 - 128 + 512);
+    assert_eq!(num_constraints.get(), 21664);
+
+    let mut h = [0u32; 8];
+    for w in h.iter_mut() {
+        *w = rng.gen();
+    }
+    let block = (0..64).map(|_| rng.gen()).collect::<Vec<u8>>();
+    let block_bits = block.iter().flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1)).collect::<Vec<_>>();
+
+    let proof = create_random_proof::<Bls12, _, _, _>(MyCompressCircuit {
+        h: h.iter().map(|&w| Assignment::known(w)).collect(),
+        block: block_bits.into_iter().map(Assignment::known).collect(),
+        num_constraints: &num_constraints
+    }, &params, rng).unwrap();
+
+    let mut expected = h;
+    compress(&mut expected, &block, 64, true);
+    assert!(input::verify_with_inputs(&ivk, &proof, &multipack::compute_multipacking::<Bls12>(&bits_of(&expected))).unwrap());
+
+    let mut wrong = h;
+    compress(&mut wrong, &block, 64, false);
+    assert!(!input::verify_with_inputs(&ivk, &proof, &multipack::compute_multipacking::<Bls12>(&bits_of(&wrong))).unwrap());
+}
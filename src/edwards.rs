@@ -0,0 +1,950 @@
+//! A gadget for points on the Jubjub curve.
+
+use pairing::*;
+use pairing::bls12_381::{Bls12, Fr};
+use bellman::*;
+
+use super::{Assignment, Bit, JubJub, LcNum, Num, Point, point_lookup};
+
+/// A point on the Jubjub curve, in affine Edwards coordinates.
+pub struct EdwardsPoint<E: Engine> {
+    x: LcNum<E>,
+    y: LcNum<E>
+}
+
+impl<E: Engine> Clone for EdwardsPoint<E> {
+    fn clone(&self) -> EdwardsPoint<E> {
+        EdwardsPoint {
+            x: self.x.clone(),
+            y: self.y.clone()
+        }
+    }
+}
+
+impl<E: Engine> EdwardsPoint<E> {
+    /// The x-coordinate, as a linear combination. Costs no
+    /// constraints.
+    pub fn get_x(&self) -> &LcNum<E> {
+        &self.x
+    }
+
+    /// The y-coordinate, as a linear combination. Costs no
+    /// constraints.
+    pub fn get_y(&self) -> &LcNum<E> {
+        &self.y
+    }
+
+    /// The x-coordinate, as a `Num`. Costs one constraint.
+    pub fn x_num<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Num<E>, Error>
+    {
+        self.x.into_num(cs)
+    }
+
+    /// The y-coordinate, as a `Num`. Costs one constraint.
+    pub fn y_num<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Num<E>, Error>
+    {
+        self.y.into_num(cs)
+    }
+
+    /// Returns `a` if `condition` is set, and `b` otherwise.
+    /// Costs two constraints.
+    pub fn conditionally_select<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        condition: &Bit,
+        a: &EdwardsPoint<E>,
+        b: &EdwardsPoint<E>
+    ) -> Result<EdwardsPoint<E>, Error>
+    {
+        Ok(EdwardsPoint {
+            x: LcNum::conditionally_select(cs, condition, &a.x, &b.x)?.into(),
+            y: LcNum::conditionally_select(cs, condition, &a.y, &b.y)?.into()
+        })
+    }
+
+    /// Returns `table[i]`, where `bits` are the little-endian bits
+    /// of `i`. Each bit halves the table by selecting from its
+    /// pairs, as in `Num::multiplex`, so this costs
+    /// `2 * (table.len() - 1)` constraints. A short table is
+    /// padded with its last entry, as there.
+    pub fn multiplex<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        bits: &[Bit],
+        table: &[EdwardsPoint<E>]
+    ) -> Result<EdwardsPoint<E>, Error>
+    {
+        assert!(!table.is_empty());
+        assert!(table.len() <= 1 << bits.len());
+
+        let mut table = table.to_vec();
+
+        for bit in bits {
+            let mut halved = Vec::with_capacity((table.len() + 1) / 2);
+            for pair in table.chunks(2) {
+                halved.push(match pair.len() {
+                    2 => EdwardsPoint::conditionally_select(cs, bit, &pair[1], &pair[0])?,
+                    _ => pair[0].clone()
+                });
+            }
+
+            table = halved;
+        }
+
+        Ok(table.pop().unwrap())
+    }
+}
+
+impl EdwardsPoint<Bls12> {
+    /// Wraps coordinates which are already known to be on the
+    /// curve, such as those from a table lookup of valid points.
+    pub fn interpret_unchecked(x: Num<Bls12>, y: Num<Bls12>) -> EdwardsPoint<Bls12> {
+        EdwardsPoint {
+            x: x.into(),
+            y: y.into()
+        }
+    }
+
+    /// The identity, `(0, 1)`. Costs no constraints.
+    pub fn identity<CS: ConstraintSystem<Bls12>>() -> EdwardsPoint<Bls12> {
+        EdwardsPoint {
+            x: LcNum::zero(),
+            y: LcNum::constant::<CS>(Fr::one())
+        }
+    }
+
+    /// Witnesses a point, enforcing that it is on the curve.
+    /// Costs three constraints.
+    pub fn witness<CS: ConstraintSystem<Bls12>>(
+        cs: &mut CS,
+        p: Assignment<Point>,
+        j: &JubJub
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+    {
+        let (x, y) = match p {
+            Assignment::Known(p) => (Assignment::known(p.x), Assignment::known(p.y)),
+            Assignment::Unknown => (Assignment::unknown(), Assignment::unknown())
+        };
+
+        let x = Num::alloc(cs, x)?;
+        let y = Num::alloc(cs, y)?;
+
+        EdwardsPoint::from_coordinates(cs, x, y, j)
+    }
+
+    /// Enforces that `(x, y)` is on the curve. Costs three
+    /// constraints.
+    fn from_coordinates<CS: ConstraintSystem<Bls12>>(
+        cs: &mut CS,
+        x: Num<Bls12>,
+        y: Num<Bls12>,
+        j: &JubJub
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+    {
+        let x2 = x.square(cs)?;
+        let y2 = y.square(cs)?;
+
+        // Constrain: (d x^2) * y^2 = y^2 - x^2 - 1
+        let one = LcNum::constant::<CS>(Fr::one());
+        let x2 = LcNum::from(x2);
+        let y2 = LcNum::from(y2);
+
+        cs.enforce(
+            x2.scale(j.d).lc(),
+            y2.lc(),
+            y2.sub(&x2).sub(&one).lc()
+        );
+
+        Ok(EdwardsPoint::interpret_unchecked(x, y))
+    }
+
+    /// Decompresses a point from the 256 bits `Point::repr_bits`
+    /// produces: the little-endian bits of y, then the lowest bit
+    /// of x. Enforces that y is canonical, that the point is on
+    /// the curve and that x has the given sign. Fails to
+    /// synthesize if the bits don't encode a point.
+    pub fn from_compressed_bits<CS: ConstraintSystem<Bls12>>(
+        cs: &mut CS,
+        bits: &[Bit],
+        j: &JubJub
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+    {
+        EdwardsPoint::decompress(cs, bits, j, |y, sign| Point::recover_x(y, sign, j))
+    }
+
+    /// `from_compressed_bits`, witnessing x with `recover_x` so
+    /// that tests can supply a dishonest one.
+    fn decompress<CS, F>(
+        cs: &mut CS,
+        bits: &[Bit],
+        j: &JubJub,
+        recover_x: F
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+        where CS: ConstraintSystem<Bls12>,
+              F: FnOnce(Fr, bool) -> Option<Fr>
+    {
+        assert_eq!(bits.len(), 256);
+
+        let y = Num::from_bits_le(cs, &bits[0..255], true)?;
+        let sign = bits[255];
+
+        let x = Num::alloc(cs, match (y.get_value(), sign.get_value()) {
+            (Assignment::Known(y), Assignment::Known(sign)) => {
+                match recover_x(y, sign) {
+                    Some(x) => Assignment::known(x),
+                    None => return Err(Error::AssignmentMissing)
+                }
+            },
+            _ => Assignment::unknown()
+        })?;
+
+        // The sign is only meaningful for a canonical x.
+        let x_bits = x.unpack_strict(cs)?;
+        LcNum::from_bit(&x_bits[0]).enforce_equal(cs, &LcNum::from_bit(&sign));
+
+        EdwardsPoint::from_coordinates(cs, x, y, j)
+    }
+
+    /// The compressed representation `from_compressed_bits`
+    /// consumes.
+    pub fn repr_bits<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        let mut bits = self.y.unpack_strict(cs)?;
+        bits.push(self.x.unpack_strict(cs)?[0]);
+
+        Ok(bits)
+    }
+
+    /// Computes `x1y2`, `y1x2`, `(x1 + y1)(x2 + y2)` and
+    /// `d x1x2y1y2` for an addition. Costs four constraints.
+    fn addition_terms<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS,
+        other: &EdwardsPoint<Bls12>,
+        j: &JubJub
+    ) -> Result<(LcNum<Bls12>, LcNum<Bls12>, LcNum<Bls12>, LcNum<Bls12>), Error>
+    {
+        let (x1, y1) = (&self.x, &self.y);
+        let (x2, y2) = (&other.x, &other.y);
+
+        // x1y2 + y1x2 and x1x2 + y1y2 are both recovered from
+        // (x1 + y1)(x2 + y2) with linear combinations, and
+        // x1x2y1y2 = (x1y2)(y1x2).
+        let x1y2 = x1.mul(cs, y2)?;
+        let y1x2 = y1.mul(cs, x2)?;
+        let u = x1.add(y1).mul(cs, &x2.add(y2))?;
+        let tau = x1y2.mul(cs, &y1x2)?;
+
+        Ok((x1y2, y1x2, u, tau.scale(j.d)))
+    }
+
+    /// Computes `self + other`. The addition law is complete, so
+    /// this works for any pair of points. Costs six constraints.
+    pub fn add<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS,
+        other: &EdwardsPoint<Bls12>,
+        j: &JubJub
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+    {
+        let (x1y2, y1x2, u, dtau) = self.addition_terms(cs, other, j)?;
+        let one = LcNum::constant::<CS>(Fr::one());
+
+        Ok(EdwardsPoint {
+            // x3 = (x1y2 + y1x2) / (1 + d tau)
+            x: x1y2.add(&y1x2).div(cs, &one.add(&dtau))?.into(),
+            // y3 = (x1x2 + y1y2) / (1 - d tau)
+            y: u.sub(&x1y2).sub(&y1x2).div(cs, &one.sub(&dtau))?.into()
+        })
+    }
+
+    /// Computes only the y-coordinate of `self + other`. Costs
+    /// five constraints.
+    pub fn add_get_y<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS,
+        other: &EdwardsPoint<Bls12>,
+        j: &JubJub
+    ) -> Result<Num<Bls12>, Error>
+    {
+        let (x1y2, y1x2, u, dtau) = self.addition_terms(cs, other, j)?;
+        let one = LcNum::constant::<CS>(Fr::one());
+
+        u.sub(&x1y2).sub(&y1x2).div(cs, &one.sub(&dtau))
+    }
+
+    /// Computes `2 * self`. Using the curve equation, the
+    /// denominators of the addition law become `y^2 - x^2` and
+    /// `2 + x^2 - y^2`, which are never zero for points on the
+    /// curve. Costs five constraints.
+    pub fn double<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS,
+        _: &JubJub
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+    {
+        let (x, y) = (&self.x, &self.y);
+
+        let xy = x.mul(cs, y)?;
+        let x2 = x.square(cs)?;
+        let y2 = y.square(cs)?;
+        let two = LcNum::constant::<CS>(Fr::from_str("2").unwrap());
+
+        Ok(EdwardsPoint {
+            // x3 = 2xy / (y^2 - x^2)
+            x: xy.scale(Fr::from_str("2").unwrap()).div(cs, &y2.sub(&x2))?.into(),
+            // y3 = (x^2 + y^2) / (2 + x^2 - y^2)
+            y: x2.add(&y2).div(cs, &two.add(&x2).sub(&y2))?.into()
+        })
+    }
+
+    /// Computes `-self`. Costs no constraints.
+    pub fn negate(&self) -> EdwardsPoint<Bls12> {
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+
+        EdwardsPoint {
+            x: self.x.scale(minus_one),
+            y: self.y.clone()
+        }
+    }
+
+    /// Multiplies this point by the scalar with little-endian
+    /// bits `by`, using double-and-add from the most significant
+    /// bit. Each bit costs a doubling, an addition and a select,
+    /// thirteen constraints, except the first which is only a
+    /// select against the identity: `13n - 11` in total for an
+    /// `n`-bit scalar.
+    pub fn mul<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS,
+        by: &[Bit],
+        j: &JubJub
+    ) -> Result<EdwardsPoint<Bls12>, Error>
+    {
+        let identity = EdwardsPoint::identity::<CS>();
+        let mut acc: Option<EdwardsPoint<Bls12>> = None;
+
+        for bit in by.iter().rev() {
+            acc = Some(match acc {
+                None => EdwardsPoint::conditionally_select(cs, bit, self, &identity)?,
+                Some(acc) => {
+                    let acc = acc.double(cs, j)?;
+                    let sum = acc.add(cs, self, j)?;
+
+                    EdwardsPoint::conditionally_select(cs, bit, &sum, &acc)?
+                }
+            });
+        }
+
+        Ok(acc.unwrap_or(identity))
+    }
+
+    /// Enforces that this point is not of small order, by
+    /// checking that multiplying it by the cofactor 8 doesn't
+    /// give a point with `x = 0`. Only the identity and the point
+    /// of order two have `x = 0`, and the latter can't be a
+    /// multiple of 8. Costs sixteen constraints.
+    pub fn assert_not_small_order<CS: ConstraintSystem<Bls12>>(
+        &self,
+        cs: &mut CS,
+        j: &JubJub
+    ) -> Result<(), Error>
+    {
+        let p = self.double(cs, j)?;
+        let p = p.double(cs, j)?;
+        let p = p.double(cs, j)?;
+
+        // x * x^-1 = 1 is unsatisfiable when x = 0.
+        LcNum::constant::<CS>(Fr::one()).div(cs, &p.x)?;
+
+        Ok(())
+    }
+}
+
+/// Precomputed window tables for multiplying a fixed base point
+/// by a scalar in the circuit.
+pub struct FixedBaseGenerator {
+    // Window `i` holds `k * 16^i * base` for `k` in `0..16`.
+    windows: Vec<(Vec<Fr>, Vec<Fr>)>
+}
+
+impl FixedBaseGenerator {
+    /// Builds tables for scalars of up to `4 * num_windows` bits.
+    pub fn new(base: &Point, num_windows: usize, j: &JubJub) -> FixedBaseGenerator {
+        let mut windows = Vec::with_capacity(num_windows);
+        let mut window_base = *base;
+
+        for _ in 0..num_windows {
+            let mut x_table = Vec::with_capacity(16);
+            let mut y_table = Vec::with_capacity(16);
+            let mut cur = Point::zero();
+
+            for _ in 0..16 {
+                x_table.push(cur.x);
+                y_table.push(cur.y);
+                cur.add_assign(&window_base, j);
+            }
+
+            // cur is now 16 * window_base.
+            window_base = cur;
+            windows.push((x_table, y_table));
+        }
+
+        FixedBaseGenerator {
+            windows: windows
+        }
+    }
+
+    pub fn num_windows(&self) -> usize {
+        self.windows.len()
+    }
+}
+
+/// Multiplies the generator's base by the scalar with
+/// little-endian bits `by`, looking up a multiple of the base for
+/// each 4-bit window and summing them. The number of bits must
+/// be a multiple of four; Jubjub scalars fit in 252.
+///
+/// Each window costs six constraints for the lookup and six for
+/// the addition, except the first which needs no addition:
+/// `12w - 6` in total for `w` windows, or three constraints per
+/// bit.
+pub fn fixed_base_mul<CS>(
+    cs: &mut CS,
+    generator: &FixedBaseGenerator,
+    by: &[Bit],
+    j: &JubJub
+) -> Result<EdwardsPoint<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    assert!(by.len() > 0);
+    assert!(by.len() % 4 == 0);
+    assert!(by.len() <= generator.windows.len() * 4);
+
+    let mut acc: Option<EdwardsPoint<Bls12>> = None;
+
+    for (chunk, &(ref x_table, ref y_table)) in by.chunks(4).zip(generator.windows.iter()) {
+        let (x, y) = point_lookup(cs, x_table, y_table, chunk)?;
+        let p = EdwardsPoint::interpret_unchecked(x, y);
+
+        acc = Some(match acc {
+            None => p,
+            Some(acc) => acc.add(cs, &p, j)?
+        });
+    }
+
+    Ok(acc.unwrap())
+}
+
+#[test]
+fn test_edwards() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+
+    let rng = &mut thread_rng();
+    let j = &JubJub::new();
+
+    struct MyEdwardsCircuit<'a> {
+        p: Assignment<Point>,
+        q: Assignment<Point>,
+        c: Assignment<bool>,
+        j: &'a JubJub
+    }
+
+    impl<'a> Circuit<Bls12> for MyEdwardsCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let p = EdwardsPoint::witness(cs, self.p, self.j)?;
+            let q = EdwardsPoint::witness(cs, self.q, self.j)?;
+            let c = Bit::alloc(cs, self.c)?;
+
+            p.assert_not_small_order(cs, self.j)?;
+
+            let minus_p = p.negate();
+
+            let results = vec![
+                p.add(cs, &q, self.j)?,
+                p.add(cs, &p, self.j)?,
+                p.double(cs, self.j)?,
+                minus_p.clone(),
+                p.add(cs, &minus_p, self.j)?,
+                EdwardsPoint::conditionally_select(cs, &c, &p, &q)?
+            ];
+
+            let sum_y = p.add_get_y(cs, &q, self.j)?;
+
+            let mut inputs = PublicInputs::new().num(&sum_y);
+            for r in &results {
+                inputs = inputs.num(r.get_x()).num(r.get_y());
+            }
+
+            Ok(inputs)
+        }
+    }
+
+    let (params, ivk) = input::generate_input_parameters(MyEdwardsCircuit {
+        p: Assignment::unknown(),
+        q: Assignment::unknown(),
+        c: Assignment::unknown(),
+        j: j
+    }, rng).unwrap();
+
+    for _ in 0..5 {
+        let p = Point::rand(rng, j);
+        let q = Point::rand(rng, j);
+        let c: bool = rng.gen();
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyEdwardsCircuit {
+            p: Assignment::known(p),
+            q: Assignment::known(q),
+            c: Assignment::known(c),
+            j: j
+        }, &params, rng).unwrap();
+
+        let mut sum = p;
+        sum.add_assign(&q, j);
+        let mut double = p;
+        double.double(j);
+        let mut neg = p;
+        neg.negate();
+
+        let expected = vec![sum, double, double, neg, Point::zero(), if c { p } else { q }];
+
+        let mut inputs = vec![sum.y];
+        for e in &expected {
+            inputs.push(e.x);
+            inputs.push(e.y);
+        }
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &inputs).unwrap());
+
+        inputs[0] = sum.x;
+        assert!(!input::verify_with_inputs(&ivk, &proof, &inputs).unwrap());
+    }
+
+    // Points off the curve can't be witnessed.
+    struct MyWitnessCircuit<'a> {
+        p: Assignment<Point>,
+        j: &'a JubJub
+    }
+
+    impl<'a> Circuit<Bls12> for MyWitnessCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let p = EdwardsPoint::witness(cs, self.p, self.j)?;
+
+            Ok(PublicInputs::new().num(&p.x_num(cs)?).num(&p.y_num(cs)?))
+        }
+    }
+
+    let (witness_params, witness_ivk) = input::generate_input_parameters(MyWitnessCircuit {
+        p: Assignment::unknown(),
+        j: j
+    }, rng).unwrap();
+
+    let on_curve = Point::rand(rng, j);
+    let proof = create_random_proof::<Bls12, _, _, _>(MyWitnessCircuit {
+        p: Assignment::known(on_curve),
+        j: j
+    }, &witness_params, rng).unwrap();
+    assert!(input::verify_with_inputs(&witness_ivk, &proof, &[on_curve.x, on_curve.y]).unwrap());
+
+    let mut off_curve = on_curve;
+    off_curve.x.add_assign(&Fr::one());
+    assert!(!off_curve.is_on_curve(j));
+
+    let proof = create_random_proof::<Bls12, _, _, _>(MyWitnessCircuit {
+        p: Assignment::known(off_curve),
+        j: j
+    }, &witness_params, rng).unwrap();
+    assert!(!input::verify_with_inputs(&witness_ivk, &proof, &[off_curve.x, off_curve.y]).unwrap());
+
+    // The identity and the point of order two are of small order.
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+
+    for small in &[Point::zero(), Point { x: Fr::zero(), y: minus_one }] {
+        assert!(small.is_on_curve(j));
+
+        assert!(create_random_proof::<Bls12, _, _, _>(MyEdwardsCircuit {
+            p: Assignment::known(*small),
+            q: Assignment::known(Point::zero()),
+            c: Assignment::known(true),
+            j: j
+        }, &params, rng).is_err());
+    }
+}
+
+#[test]
+fn test_edwards_mul() {
+    use bellman::groth16::*;
+    use rand::{Rand, thread_rng};
+    use input::{self, PublicInputs};
+    use super::ConstraintCounter;
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+    let j = &JubJub::new();
+
+    struct MyMulCircuit<'a> {
+        p: Assignment<Point>,
+        scalar: Vec<Assignment<bool>>,
+        j: &'a JubJub,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyMulCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let p = EdwardsPoint::witness(cs, self.p, self.j)?;
+
+            let mut bits = vec![];
+            for b in self.scalar {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let res = p.mul(&mut counter, &bits, self.j)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(res.get_x()).num(res.get_y()))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let (params, ivk) = input::generate_input_parameters(MyMulCircuit {
+        p: Assignment::unknown(),
+        scalar: vec![Assignment::unknown(); 255],
+        j: j,
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+
+    assert_eq!(num_constraints.get(), 13 * 255 - 11);
+
+    let mut scalars = vec![Fr::zero(), Fr::one()];
+    scalars.push(Fr::rand(rng));
+    scalars.push(Fr::rand(rng));
+
+    for scalar in scalars {
+        let p = Point::rand(rng, j);
+
+        let mut bits = BitIterator::new(scalar.into_repr()).collect::<Vec<_>>();
+        bits.reverse();
+        bits.truncate(255);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyMulCircuit {
+            p: Assignment::known(p),
+            scalar: bits.into_iter().map(Assignment::known).collect(),
+            j: j,
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        let mut expected = p;
+        expected.mul_assign(&scalar, j);
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[expected.x, expected.y]).unwrap());
+
+        let mut wrong = expected;
+        wrong.add_assign(&p, j);
+
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[wrong.x, wrong.y]).unwrap());
+    }
+}
+
+#[test]
+fn test_fixed_base_mul() {
+    use bellman::groth16::*;
+    use rand::{Rand, thread_rng};
+    use input::{self, PublicInputs};
+    use super::ConstraintCounter;
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+    let j = &JubJub::new();
+
+    let generator = FixedBaseGenerator::new(&Point::rand(rng, j), 63, j);
+    let base = Point {
+        x: generator.windows[0].0[1],
+        y: generator.windows[0].1[1]
+    };
+
+    struct MyFixedBaseCircuit<'a> {
+        generator: &'a FixedBaseGenerator,
+        scalar: Vec<Assignment<bool>>,
+        j: &'a JubJub,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyFixedBaseCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut bits = vec![];
+            for b in self.scalar {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let res = fixed_base_mul(&mut counter, self.generator, &bits, self.j)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(res.get_x()).num(res.get_y()))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let (params, ivk) = input::generate_input_parameters(MyFixedBaseCircuit {
+        generator: &generator,
+        scalar: vec![Assignment::unknown(); 252],
+        j: j,
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+
+    assert_eq!(num_constraints.get(), 12 * 63 - 6);
+
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+
+    let mut scalars = vec![Fr::zero(), Fr::one(), minus_one];
+    scalars.push(Fr::rand(rng));
+    scalars.push(Fr::rand(rng));
+
+    for scalar in scalars {
+        // Truncate to 252 bits.
+        let mut repr = scalar.into_repr();
+        repr.as_mut()[3] &= (1 << 60) - 1;
+        let scalar = Fr::from_repr(repr).unwrap();
+
+        let mut bits = BitIterator::new(repr).collect::<Vec<_>>();
+        bits.reverse();
+        bits.truncate(252);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyFixedBaseCircuit {
+            generator: &generator,
+            scalar: bits.into_iter().map(Assignment::known).collect(),
+            j: j,
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        let mut expected = base;
+        expected.mul_assign(&scalar, j);
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[expected.x, expected.y]).unwrap());
+
+        let mut wrong = expected;
+        wrong.negate();
+        wrong.add_assign(&base, j);
+
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[wrong.x, wrong.y]).unwrap());
+    }
+}
+
+#[test]
+fn test_compressed_bits() {
+    use bellman::groth16::*;
+    use rand::thread_rng;
+    use input::{self, PublicInputs};
+
+    let rng = &mut thread_rng();
+    let j = &JubJub::new();
+
+    struct MyCompressionCircuit<'a> {
+        bits: Vec<Assignment<bool>>,
+        // Witness x with the other sign, as a dishonest prover.
+        flip_x: bool,
+        j: &'a JubJub
+    }
+
+    impl<'a> Circuit<Bls12> for MyCompressionCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut bits = vec![];
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            let (flip_x, j) = (self.flip_x, self.j);
+            let p = EdwardsPoint::decompress(cs, &bits, j, |y, sign| {
+                Point::recover_x(y, sign != flip_x, j)
+            })?;
+            let repr = p.repr_bits(cs)?;
+
+            Ok(PublicInputs::new()
+                .num(p.get_x())
+                .num(p.get_y())
+                .packed_bits(&bits)
+                .packed_bits(&repr))
+        }
+    }
+
+    let (params, ivk) = input::generate_input_parameters(MyCompressionCircuit {
+        bits: vec![Assignment::unknown(); 256],
+        flip_x: false,
+        j: j
+    }, rng).unwrap();
+
+    let prove = |bits: &[bool], flip_x: bool, rng: &mut ::rand::ThreadRng| {
+        create_random_proof::<Bls12, _, _, _>(MyCompressionCircuit {
+            bits: bits.iter().map(|&b| Assignment::known(b)).collect(),
+            flip_x: flip_x,
+            j: j
+        }, &params, rng)
+    };
+
+    // The inputs for a proof that `bits` decompress to `p`.
+    let inputs_for = |p: &Point, bits: &[bool]| {
+        let mut inputs = vec![p.x, p.y];
+        inputs.extend(::multipack::compute_multipacking::<Bls12>(bits));
+        inputs.extend(::multipack::compute_multipacking::<Bls12>(&p.repr_bits()));
+        inputs
+    };
+
+    for _ in 0..5 {
+        let p = Point::rand(rng, j);
+        let bits = p.repr_bits();
+
+        assert!(Point::from_compressed_bits(&bits, j) == Some(p));
+
+        let proof = prove(&bits, false, rng).unwrap();
+        assert!(input::verify_with_inputs(&ivk, &proof, &inputs_for(&p, &bits)).unwrap());
+
+        // The other sign gives the negated point.
+        let mut flipped = bits.clone();
+        flipped[255] = !flipped[255];
+
+        let mut neg = p;
+        neg.negate();
+        assert!(Point::from_compressed_bits(&flipped, j) == Some(neg));
+
+        let proof = prove(&flipped, false, rng).unwrap();
+        assert!(input::verify_with_inputs(&ivk, &proof, &inputs_for(&neg, &flipped)).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &inputs_for(&p, &bits)).unwrap());
+
+        // A witness x of the other sign must not satisfy the
+        // constraints, neither for the honest point nor for the
+        // negated point it actually is.
+        let proof = prove(&bits, true, rng).unwrap();
+        assert!(!input::verify_with_inputs(&ivk, &proof, &inputs_for(&p, &bits)).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &inputs_for(&neg, &bits)).unwrap());
+    }
+
+    // The bits of r encode y = 0 non-canonically, and must be
+    // rejected.
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    let order_two = Point { x: Fr::zero(), y: minus_one };
+    let mut r_bits = order_two.repr_bits();
+    {
+        // Add one to the bits of r - 1.
+        let mut i = 0;
+        while r_bits[i] {
+            r_bits[i] = false;
+            i += 1;
+        }
+        r_bits[i] = true;
+    }
+
+    assert!(Point::from_compressed_bits(&r_bits, j).is_none());
+
+    // An honest prover can still witness x for y = 0, so only
+    // the in-circuit canonicity check stops this proof.
+    let zero_y = Point {
+        x: Point::recover_x(Fr::zero(), r_bits[255], j).unwrap(),
+        y: Fr::zero()
+    };
+    assert!(zero_y.is_on_curve(j));
+
+    let proof = prove(&r_bits, false, rng).unwrap();
+    assert!(!input::verify_with_inputs(&ivk, &proof, &inputs_for(&zero_y, &r_bits)).unwrap());
+    assert!(!input::verify_with_inputs(&ivk, &proof, &inputs_for(&order_two, &r_bits)).unwrap());
+
+    // The point of order two, (0, -1), has no odd x.
+    let mut odd_zero = order_two.repr_bits();
+    assert!(Point::from_compressed_bits(&odd_zero, j).is_some());
+    odd_zero[255] = true;
+    assert!(Point::from_compressed_bits(&odd_zero, j).is_none());
+    assert!(prove(&odd_zero, false, rng).is_err());
+}
+
+#[test]
+fn test_edwards_multiplex() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+
+    let rng = &mut thread_rng();
+    let j = &JubJub::new();
+
+    struct MyMultiplexCircuit<'a> {
+        table: Vec<Assignment<Point>>,
+        bits: Vec<Assignment<bool>>,
+        j: &'a JubJub
+    }
+
+    impl<'a> Circuit<Bls12> for MyMultiplexCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut table = vec![];
+            for p in self.table {
+                table.push(EdwardsPoint::witness(cs, p, self.j)?);
+            }
+
+            let mut bits = vec![];
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            let p = EdwardsPoint::multiplex(cs, &bits, &table)?;
+
+            Ok(PublicInputs::new().num(p.get_x()).num(p.get_y()))
+        }
+    }
+
+    // A short table is padded with its last entry.
+    for &size in &[4, 3] {
+        let (params, ivk) = input::generate_input_parameters(MyMultiplexCircuit {
+            table: vec![Assignment::unknown(); size],
+            bits: vec![Assignment::unknown(); 2],
+            j: j
+        }, rng).unwrap();
+
+        let table = (0..size).map(|_| Point::rand(rng, j)).collect::<Vec<_>>();
+
+        for _ in 0..3 {
+            let i = rng.gen_range(0, 4);
+            let k = ::std::cmp::min(i, size - 1);
+            let other = (k + 1) % size;
+
+            let proof = create_random_proof::<Bls12, _, _, _>(MyMultiplexCircuit {
+                table: table.iter().map(|p| Assignment::known(p.clone())).collect(),
+                bits: (0..2).map(|b| Assignment::known(i & (1 << b) != 0)).collect(),
+                j: j
+            }, &params, rng).unwrap();
+
+            assert!(input::verify_with_inputs(&ivk, &proof, &[table[k].x, table[k].y]).unwrap());
+            assert!(!input::verify_with_inputs(&ivk, &proof, &[table[other].x, table[other].y]).unwrap());
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Input maps built from lists of public numbers and bits.
+//!
+//! A circuit returns `PublicInputs` from `synthesize` instead of
+//! defining its own input map. Generating parameters through
+//! `generate_input_parameters` records the order and kind of
+//! the inputs, so the verifier only needs their values.
+
+use pairing::*;
+use bellman::*;
+use bellman::groth16::*;
+use rand::Rng;
+
+use std::cell::RefCell;
+
+use super::{Assignment, Bit, Boolean, LcNum, Num};
+use multipack;
+
+/// The kind of a public input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    /// An arbitrary field element.
+    Num,
+    /// A field element that must be zero or one.
+    Bit
+}
+
+/// Errors arising when verifying a proof against a list of
+/// public input values.
+#[derive(Debug)]
+pub enum InputError {
+    /// The number of values doesn't match the circuit's inputs.
+    WrongNumberOfInputs { expected: usize, actual: usize },
+    /// The value for the input at `index` should have been a
+    /// bit, but was neither zero nor one.
+    NotABit { index: usize },
+    /// The verifying key doesn't match the recorded layout.
+    MalformedVerifyingKey,
+    Synthesis(Error)
+}
+
+impl From<Error> for InputError {
+    fn from(e: Error) -> InputError {
+        match e {
+            Error::MalformedVerifyingKey => InputError::MalformedVerifyingKey,
+            e => InputError::Synthesis(e)
+        }
+    }
+}
+
+/// An input map which exposes the numbers and bits it was given,
+/// in order.
+pub struct PublicInputs<E: Engine> {
+    inputs: Vec<(InputKind, LcNum<E>)>
+}
+
+impl<E: Engine> PublicInputs<E> {
+    pub fn new() -> PublicInputs<E> {
+        PublicInputs {
+            inputs: vec![]
+        }
+    }
+
+    /// Exposes a `Num` or an `LcNum`.
+    pub fn num<N: Clone + Into<LcNum<E>>>(mut self, num: &N) -> PublicInputs<E> {
+        self.inputs.push((InputKind::Num, num.clone().into()));
+
+        self
+    }
+
+    pub fn bit(mut self, bit: &Bit) -> PublicInputs<E> {
+        self.inputs.push((InputKind::Bit, LcNum::from_bit(bit)));
+
+        self
+    }
+
+    pub fn boolean<CS: ConstraintSystem<E>>(mut self, b: &Boolean) -> PublicInputs<E> {
+        self.inputs.push((InputKind::Bit, b.lc::<E, CS>()));
+
+        self
+    }
+
+    pub fn bits(self, bits: &[Bit]) -> PublicInputs<E> {
+        bits.iter().fold(self, |inputs, bit| inputs.bit(bit))
+    }
+
+    /// Exposes `bits` multipacked into as few inputs as possible.
+    /// The verifier supplies `multipack::compute_multipacking` of
+    /// the bits.
+    pub fn packed_bits(mut self, bits: &[Bit]) -> PublicInputs<E> {
+        for chunk in bits.chunks(multipack::chunk_size::<E>()) {
+            self.inputs.push((InputKind::Num, LcNum::from_bits_le(chunk)));
+        }
+
+        self
+    }
+
+    /// The same as `packed_bits`, for booleans.
+    pub fn packed_booleans<CS: ConstraintSystem<E>>(mut self, bits: &[Boolean]) -> PublicInputs<E> {
+        for chunk in bits.chunks(multipack::chunk_size::<E>()) {
+            self.inputs.push((InputKind::Num, LcNum::from_booleans_le::<CS>(chunk)));
+        }
+
+        self
+    }
+
+    pub fn layout(&self) -> Vec<InputKind> {
+        self.inputs.iter().map(|&(kind, _)| kind).collect()
+    }
+}
+
+impl<E: Engine> Input<E> for PublicInputs<E> {
+    fn synthesize<CS: PublicConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), Error>
+    {
+        for (_, num) in self.inputs {
+            num.inputize(cs)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A prepared verifying key, together with the layout of the
+/// circuit's public inputs.
+pub struct InputVerifyingKey<E: Engine> {
+    pvk: PreparedVerifyingKey<E>,
+    layout: Vec<InputKind>
+}
+
+impl<E: Engine> InputVerifyingKey<E> {
+    pub fn new(vk: &VerifyingKey<E>, layout: Vec<InputKind>) -> InputVerifyingKey<E> {
+        InputVerifyingKey {
+            pvk: prepare_verifying_key(vk),
+            layout: layout
+        }
+    }
+
+    pub fn layout(&self) -> &[InputKind] {
+        &self.layout
+    }
+}
+
+/// Records the layout of the input map a circuit returns.
+struct LayoutRecorder<'a, C> {
+    circuit: C,
+    layout: &'a RefCell<Vec<InputKind>>
+}
+
+impl<'a, E: Engine, C: Circuit<E, InputMap=PublicInputs<E>>> Circuit<E> for LayoutRecorder<'a, C> {
+    type InputMap = PublicInputs<E>;
+
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+    {
+        let inputs = self.circuit.synthesize(cs)?;
+
+        *self.layout.borrow_mut() = inputs.layout();
+
+        Ok(inputs)
+    }
+}
+
+/// Generates parameters for a circuit, along with a verifying
+/// key that knows the layout of its public inputs.
+pub fn generate_input_parameters<E, C, R>(
+    circuit: C,
+    rng: &mut R
+) -> Result<(Parameters<E>, InputVerifyingKey<E>), Error>
+    where E: Engine, C: Circuit<E, InputMap=PublicInputs<E>>, R: Rng
+{
+    let layout = RefCell::new(vec![]);
+
+    let params = generate_random_parameters(LayoutRecorder {
+        circuit: circuit,
+        layout: &layout
+    }, rng)?;
+
+    let ivk = InputVerifyingKey::new(&params.vk, layout.into_inner());
+
+    Ok((params, ivk))
+}
+
+/// Verifies a proof against the values of its public inputs,
+/// given in the order the circuit exposed them.
+pub fn verify_with_inputs<E: Engine>(
+    ivk: &InputVerifyingKey<E>,
+    proof: &Proof<E>,
+    inputs: &[E::Fr]
+) -> Result<bool, InputError>
+{
+    if inputs.len() != ivk.layout.len() {
+        return Err(InputError::WrongNumberOfInputs {
+            expected: ivk.layout.len(),
+            actual: inputs.len()
+        });
+    }
+
+    for (index, (kind, value)) in ivk.layout.iter().zip(inputs.iter()).enumerate() {
+        if *kind == InputKind::Bit && !value.is_zero() && *value != E::Fr::one() {
+            return Err(InputError::NotABit { index: index });
+        }
+    }
+
+    Ok(verify_proof(&ivk.pvk, proof, |cs| {
+        let mut map = PublicInputs::new();
+
+        for value in inputs {
+            map = map.num(&Num::alloc(cs, Assignment::known(*value))?);
+        }
+
+        Ok(map)
+    })?)
+}
@@ -12,6 +12,24 @@ use pairing::bls12_381::{Fr, Bls12};
 use bellman::*;
 use rand::{Rng, Rand, thread_rng};
 
+pub mod blake2s;
+pub mod edwards;
+pub mod input;
+pub mod merkle;
+pub mod mimc;
+pub mod multipack;
+pub mod poseidon;
+pub mod rescue;
+pub mod sha256;
+pub mod sparse_merkle;
+pub mod sponge;
+pub mod tree;
+pub mod uint32;
+
+use edwards::EdwardsPoint;
+use input::PublicInputs;
+pub use mimc::{mimc, MiMCDemo, MimcParams};
+
 
 // Synthesize the constants for each base pattern.
 fn synth<E: Engine>(
@@ -105,129 +123,1245 @@ impl Bit {
             }
         })?;
 
-        // Constrain: (1 - a) * a = 0
+        // Constrain: (1 - a) * a = 0
+        cs.enforce(
+            LinearCombination::zero() + CS::one() - var,
+            LinearCombination::zero() + var,
+            LinearCombination::zero()
+        );
+
+        Ok(Bit(var, value))
+    }
+
+    pub fn get_value(&self) -> Assignment<bool> {
+        self.1
+    }
+
+    /// Exposes this bit as a public input. Costs one constraint.
+    pub fn inputize<E: Engine, CS: PublicConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<(), Error>
+    {
+        let value = self.1;
+        let input = cs.alloc_input(|| {
+            if *value.get()? {
+                Ok(E::Fr::one())
+            } else {
+                Ok(E::Fr::zero())
+            }
+        })?;
+
+        cs.enforce(
+            LinearCombination::zero() + self.0,
+            LinearCombination::zero() + CS::one(),
+            LinearCombination::zero() + input
+        );
+
+        Ok(())
+    }
+
+    fn and<E, CS>(&self, cs: &mut CS, other: &Bit) -> Result<Bit, Error>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        match (*self, *other) {
+            (Bit(a_var, a_val), Bit(b_var, b_val)) => {
+                let mut c_val = Assignment::unknown();
+
+                let c_var = cs.alloc(|| {
+                    if *a_val.get()? && *b_val.get()? {
+                        c_val = Assignment::known(true);
+                        Ok(E::Fr::one())
+                    } else {
+                        c_val = Assignment::known(false);
+                        Ok(E::Fr::zero())
+                    }
+                })?;
+
+                cs.enforce(
+                    LinearCombination::zero() + a_var,
+                    LinearCombination::zero() + b_var,
+                    LinearCombination::zero() + c_var
+                );
+
+                Ok(Bit(c_var, c_val))
+            }
+        }
+    }
+}
+
+pub struct Num<E: Engine> {
+    value: Assignment<E::Fr>,
+    var: Variable
+}
+
+/// Allocates the `num_bits` least significant bits of `num`, in
+/// little-endian order.
+fn assignment_into_bits<E: Engine, CS: ConstraintSystem<E>>(num: &Assignment<E::Fr>, num_bits: usize, cs: &mut CS) -> Result<Vec<Bit>, Error>
+{
+    Ok(match num.get() {
+        Ok(num) => {
+            let mut res_assignment = vec![];
+            for b in BitIterator::new(num.into_repr()) {
+                res_assignment.push(Assignment::known(b));
+            }
+            res_assignment.reverse();
+            res_assignment.truncate(num_bits);
+
+            let mut res_bits = vec![];
+            for b in res_assignment {
+                res_bits.push(Bit::alloc(cs, b)?);
+            }
+            res_bits
+        },
+        Err(_) => {
+            let mut res_bits = vec![];
+
+            for _ in 0..num_bits {
+                res_bits.push(Bit::alloc(cs, Assignment::unknown())?);
+            }
+
+            res_bits
+        }
+    })
+}
+
+impl<E: Engine> Num<E> {
+    /// Allocates a private field element. Costs no constraints.
+    pub fn alloc<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        value: Assignment<E::Fr>
+    ) -> Result<Num<E>, Error>
+    {
+        let var = cs.alloc(|| {
+            Ok(*value.get()?)
+        })?;
+
+        Ok(Num {
+            value: value,
+            var: var
+        })
+    }
+
+    /// Allocates a public input. Costs no constraints.
+    pub fn alloc_input<CS: PublicConstraintSystem<E>>(
+        cs: &mut CS,
+        value: Assignment<E::Fr>
+    ) -> Result<Num<E>, Error>
+    {
+        let var = cs.alloc_input(|| {
+            Ok(*value.get()?)
+        })?;
+
+        Ok(Num {
+            value: value,
+            var: var
+        })
+    }
+
+    /// Allocates a variable fixed to a constant. Costs one constraint.
+    pub fn constant<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        value: E::Fr
+    ) -> Result<Num<E>, Error>
+    {
+        let num = Num::alloc(cs, Assignment::known(value))?;
+
+        cs.enforce(
+            LinearCombination::zero() + num.var,
+            LinearCombination::zero() + CS::one(),
+            LinearCombination::zero() + (value, CS::one())
+        );
+
+        Ok(num)
+    }
+
+    pub fn get_value(&self) -> Assignment<E::Fr> {
+        self.value
+    }
+
+    pub fn get_variable(&self) -> Variable {
+        self.var
+    }
+
+    /// Exposes this number as a public input. Costs one constraint.
+    pub fn inputize<CS: PublicConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<(), Error>
+    {
+        let input = Num::alloc_input(cs, self.value)?;
+
+        self.enforce_equal(cs, &input);
+
+        Ok(())
+    }
+
+    /// Packs little-endian `bits` into a number. Costs one
+    /// constraint. If `check_canonical` is set and there are as
+    /// many bits as `Fr::num_bits()`, they are also checked to be
+    /// less than the modulus, so that the decomposition is unique.
+    pub fn from_bits_le<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        bits: &[Bit],
+        check_canonical: bool
+    ) -> Result<Num<E>, Error>
+    {
+        assert!(bits.len() <= E::Fr::num_bits() as usize);
+
+        if check_canonical && bits.len() == E::Fr::num_bits() as usize {
+            assert_less_than_r(bits, cs)?;
+        }
+
+        LcNum::from_bits_le(bits).into_num(cs)
+    }
+
+    /// Same as `from_bits_le`, with the most significant bit
+    /// first.
+    pub fn from_bits_be<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        bits: &[Bit],
+        check_canonical: bool
+    ) -> Result<Num<E>, Error>
+    {
+        let bits = bits.iter().rev().cloned().collect::<Vec<_>>();
+
+        Num::from_bits_le(cs, &bits, check_canonical)
+    }
+
+    /// Same as `unpack_le`. All of the unpacking methods return
+    /// bits in little-endian order.
+    pub fn unpack<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        self.unpack_le(cs)
+    }
+
+    /// Same as `unpack_strict`.
+    pub fn unpack_le<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        self.unpack_strict(cs)
+    }
+
+    /// Same as `unpack_strict`, with the most significant bit
+    /// first.
+    pub fn unpack_be<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        let mut bits = self.unpack_strict(cs)?;
+        bits.reverse();
+
+        Ok(bits)
+    }
+
+    /// Allocates `num_bits` little-endian bits and enforces that
+    /// they pack into this number.
+    fn unpack_into<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        num_bits: usize
+    ) -> Result<Vec<Bit>, Error>
+    {
+        LcNum::from(self.clone()).unpack_into(cs, num_bits)
+    }
+
+    /// Unpacks into the unique little-endian bit decomposition
+    /// of `Fr::num_bits()` bits that is less than the modulus.
+    /// Costs `Fr::num_bits() + 1` constraints, plus those of the
+    /// comparison with the modulus.
+    pub fn unpack_strict<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        LcNum::from(self.clone()).unpack_strict(cs)
+    }
+
+    /// Unpacks into `Fr::num_bits()` little-endian bits without
+    /// checking that they are less than the modulus, so a number
+    /// may have two valid decompositions. Costs
+    /// `Fr::num_bits() + 1` constraints.
+    pub fn unpack_nonstrict<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        self.unpack_into(cs, E::Fr::num_bits() as usize)
+    }
+
+    /// Unpacks into `num_bits` little-endian bits, which also
+    /// checks that this number is less than `2^num_bits`; the
+    /// constraints are unsatisfiable otherwise. `num_bits` can be
+    /// at most `Fr::capacity()`, so that the decomposition can't
+    /// wrap around the modulus. Costs `num_bits + 1` constraints.
+    pub fn unpack_bounded<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        num_bits: usize
+    ) -> Result<Vec<Bit>, Error>
+    {
+        assert!(num_bits <= E::Fr::capacity() as usize);
+
+        self.unpack_into(cs, num_bits)
+    }
+
+    /// Returns whether this number is less than `other`, where both
+    /// must be less than `2^num_bits`; the constraints are
+    /// unsatisfiable otherwise. Costs `2 * (num_bits + 1)`
+    /// constraints for the range checks, plus at most four per bit.
+    pub fn less_than<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &Num<E>,
+        num_bits: usize
+    ) -> Result<Boolean, Error>
+    {
+        let a = self.unpack_bounded(cs, num_bits)?;
+        let b = other.unpack_bounded(cs, num_bits)?;
+
+        Boolean::less_than(
+            cs,
+            &a.into_iter().map(Boolean::from_bit).collect::<Vec<_>>(),
+            &b.into_iter().map(Boolean::from_bit).collect::<Vec<_>>()
+        )
+    }
+
+    /// Returns whether this number is less than `constant`, where
+    /// this number must be less than `2^num_bits`. Costs
+    /// `num_bits + 1` constraints for the range check, plus at most
+    /// two per bit.
+    pub fn less_than_constant<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        constant: E::Fr,
+        num_bits: usize
+    ) -> Result<Boolean, Error>
+    {
+        let bits = self.unpack_bounded(cs, num_bits)?;
+
+        Boolean::less_than_constant(
+            cs,
+            &bits.into_iter().map(Boolean::from_bit).collect::<Vec<_>>(),
+            constant.into_repr()
+        )
+    }
+
+    /// Enforces `lo <= self <= hi`, where `hi - lo` must be less
+    /// than `2^Fr::capacity()`. Costs `n + 1` constraints, where
+    /// `n` is the bit length of `hi - lo`, plus at most two per bit.
+    pub fn assert_in_range<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        lo: E::Fr,
+        hi: E::Fr
+    ) -> Result<(), Error>
+    {
+        assert!(lo.into_repr() <= hi.into_repr());
+
+        let mut width = hi;
+        width.sub_assign(&lo);
+
+        let num_bits = BitIterator::new(width.into_repr()).skip_while(|b| !b).count();
+
+        // self - lo must be at most hi - lo.
+        let diff = LcNum::from(self.clone()).sub(&LcNum::constant::<CS>(lo));
+        let bits = diff.unpack_bounded(cs, num_bits)?;
+
+        let mut bound = width.into_repr();
+        bound.add_nocarry(&1.into());
+
+        Boolean::less_than_constant(
+            cs,
+            &bits.into_iter().map(Boolean::from_bit).collect::<Vec<_>>(),
+            bound
+        )?.assert_is_true(cs);
+
+        Ok(())
+    }
+
+    /// Computes `self + other`. Costs no constraints.
+    pub fn add(&self, other: &Num<E>) -> LcNum<E> {
+        LcNum::from(self.clone()).add(&other.clone().into())
+    }
+
+    /// Computes `self - other`. Costs no constraints.
+    pub fn sub(&self, other: &Num<E>) -> LcNum<E> {
+        LcNum::from(self.clone()).sub(&other.clone().into())
+    }
+
+    /// Computes `self * by` for a constant `by`. Costs no
+    /// constraints.
+    pub fn scale(&self, by: E::Fr) -> LcNum<E> {
+        LcNum::from(self.clone()).scale(by)
+    }
+
+    /// Computes `self * other`. Costs one constraint.
+    pub fn mul<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &Num<E>
+    ) -> Result<Num<E>, Error>
+    {
+        let mut result_value = Assignment::unknown();
+        let result_var = cs.alloc(|| {
+            let mut e = *self.value.get()?;
+            e.mul_assign(other.value.get()?);
+
+            result_value = Assignment::known(e);
+
+            Ok(e)
+        })?;
+
+        cs.enforce(
+            LinearCombination::zero() + self.var,
+            LinearCombination::zero() + other.var,
+            LinearCombination::zero() + result_var
+        );
+
+        Ok(Num {
+            value: result_value,
+            var: result_var
+        })
+    }
+
+    /// Computes `self^2`. Costs one constraint.
+    pub fn square<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Num<E>, Error>
+    {
+        self.mul(cs, self)
+    }
+
+    /// Computes `self^-1`. Costs one constraint, which is
+    /// unsatisfiable when `self` is zero. No witness exists then,
+    /// so proving fails; bellman has no error for that, and it is
+    /// reported as `Error::AssignmentMissing`.
+    pub fn inverse<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Num<E>, Error>
+    {
+        let mut result_value = Assignment::unknown();
+        let result_var = cs.alloc(|| {
+            let e = self.value.get()?.inverse().ok_or(Error::AssignmentMissing)?;
+
+            result_value = Assignment::known(e);
+
+            Ok(e)
+        })?;
+
+        cs.enforce(
+            LinearCombination::zero() + self.var,
+            LinearCombination::zero() + result_var,
+            LinearCombination::zero() + CS::one()
+        );
+
+        Ok(Num {
+            value: result_value,
+            var: result_var
+        })
+    }
+
+    /// Computes `self / other`. Costs one constraint. The
+    /// constraint `result * other = self` is unsatisfiable when
+    /// `other` is zero and `self` is not, but places no
+    /// restriction on `result` when both are zero. Either way the
+    /// prover can't divide by zero, and fails as for `inverse`.
+    pub fn div<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &Num<E>
+    ) -> Result<Num<E>, Error>
+    {
+        let mut result_value = Assignment::unknown();
+        let result_var = cs.alloc(|| {
+            let mut e = other.value.get()?.inverse().ok_or(Error::AssignmentMissing)?;
+            e.mul_assign(self.value.get()?);
+
+            result_value = Assignment::known(e);
+
+            Ok(e)
+        })?;
+
+        cs.enforce(
+            LinearCombination::zero() + result_var,
+            LinearCombination::zero() + other.var,
+            LinearCombination::zero() + self.var
+        );
+
+        Ok(Num {
+            value: result_value,
+            var: result_var
+        })
+    }
+
+    /// Returns `a` if `condition` is set, and `b` otherwise.
+    /// Costs one constraint.
+    pub fn conditionally_select<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        condition: &Bit,
+        a: &Num<E>,
+        b: &Num<E>
+    ) -> Result<Num<E>, Error>
+    {
+        LcNum::conditionally_select(cs, condition, &a.clone().into(), &b.clone().into())
+    }
+
+    /// Returns `(b, a)` if `condition` is set, and `(a, b)`
+    /// otherwise. Costs one constraint, to select the first
+    /// result; the second is the sum of both less the first.
+    pub fn swap_if<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        condition: &Bit,
+        a: &Num<E>,
+        b: &Num<E>
+    ) -> Result<(Num<E>, LcNum<E>), Error>
+    {
+        LcNum::swap_if(cs, condition, &a.clone().into(), &b.clone().into())
+    }
+
+    /// Returns `table[i]`, where `bits` are the little-endian bits
+    /// of `i`. This is `coordinate_lookup` for a table of
+    /// variables rather than constants: each bit halves the table
+    /// by selecting from its pairs, so this costs
+    /// `table.len() - 1` constraints.
+    ///
+    /// A table shorter than `1 << bits.len()` is padded with its
+    /// last entry, which is carried up unselected, so indices past
+    /// its end return that entry at no extra cost.
+    pub fn multiplex<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        bits: &[Bit],
+        table: &[Num<E>]
+    ) -> Result<Num<E>, Error>
+    {
+        assert!(!table.is_empty());
+        assert!(table.len() <= 1 << bits.len());
+
+        let mut table = table.to_vec();
+
+        for bit in bits {
+            let mut halved = Vec::with_capacity((table.len() + 1) / 2);
+            for pair in table.chunks(2) {
+                halved.push(match pair.len() {
+                    2 => Num::conditionally_select(cs, bit, &pair[1], &pair[0])?,
+                    _ => pair[0].clone()
+                });
+            }
+
+            table = halved;
+        }
+
+        Ok(table.pop().unwrap())
+    }
+
+    /// Enforces `self = other`. Costs one constraint.
+    pub fn enforce_equal<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &Num<E>
+    )
+    {
+        cs.enforce(
+            LinearCombination::zero() + self.var - other.var,
+            LinearCombination::zero() + CS::one(),
+            LinearCombination::zero()
+        );
+    }
+
+    /// Returns a bit that is set iff `self` is zero. Costs two
+    /// constraints.
+    pub fn is_zero<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Bit, Error>
+    {
+        is_zero_lc(cs, LinearCombination::zero() + self.var, self.value)
+    }
+
+    /// Returns a bit that is set iff `self = other`. Costs two
+    /// constraints.
+    pub fn equals<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &Num<E>
+    ) -> Result<Bit, Error>
+    {
+        let diff = match (self.value, other.value) {
+            (Assignment::Known(mut a), Assignment::Known(b)) => {
+                a.sub_assign(&b);
+                Assignment::known(a)
+            },
+            _ => Assignment::unknown()
+        };
+
+        is_zero_lc(cs, LinearCombination::zero() + self.var - other.var, diff)
+    }
+}
+
+/// Returns a bit that is set iff `lc`, whose assignment is `value`,
+/// evaluates to zero.
+fn is_zero_lc<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    lc: LinearCombination<E>,
+    value: Assignment<E::Fr>
+) -> Result<Bit, Error>
+{
+    let mut r_val = Assignment::unknown();
+    let r = cs.alloc(|| {
+        if value.get()?.is_zero() {
+            r_val = Assignment::known(true);
+            Ok(E::Fr::one())
+        } else {
+            r_val = Assignment::known(false);
+            Ok(E::Fr::zero())
+        }
+    })?;
+
+    let inv = cs.alloc(|| {
+        Ok(value.get()?.inverse().unwrap_or(E::Fr::zero()))
+    })?;
+
+    // Constrain: lc * inv = 1 - r
+    // If lc is nonzero, this forces r = 0 (with inv = 1/lc).
+    // If lc is zero, this forces r = 1.
+    cs.enforce(
+        lc.clone(),
+        LinearCombination::zero() + inv,
+        LinearCombination::zero() + CS::one() - r
+    );
+
+    // Constrain: lc * r = 0
+    // This prevents r = 1 when lc is nonzero.
+    cs.enforce(
+        lc,
+        LinearCombination::zero() + r,
+        LinearCombination::zero()
+    );
+
+    Ok(Bit(r, r_val))
+}
+
+#[test]
+fn test_num() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+
+    let rng = &mut thread_rng();
+
+    struct MyNumCircuit {
+        a: Assignment<Fr>,
+        b: Assignment<Fr>,
+        c: Assignment<bool>,
+        expected: Vec<Fr>,
+        expected_bits: Vec<bool>
+    }
+
+    struct MyNumCircuitInput;
+
+    impl<E: Engine> Input<E> for MyNumCircuitInput {
+        fn synthesize<CS: PublicConstraintSystem<E>>(self, _: &mut CS) -> Result<(), Error>
+        {
+            Ok(())
+        }
+    }
+
+    impl Circuit<Bls12> for MyNumCircuit {
+        type InputMap = MyNumCircuitInput;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let a = Num::alloc(cs, self.a)?;
+            let b = Num::alloc(cs, self.b)?;
+            let c = Bit::alloc(cs, self.c)?;
+
+            let results: Vec<LcNum<Bls12>> = vec![
+                a.add(&b),
+                a.sub(&b),
+                a.scale(Fr::from_str("7").unwrap()),
+                a.mul(cs, &b)?.into(),
+                a.square(cs)?.into(),
+                a.inverse(cs)?.into(),
+                a.div(cs, &b)?.into(),
+                Num::conditionally_select(cs, &c, &a, &b)?.into()
+            ];
+
+            for (r, e) in results.iter().zip(self.expected.iter()) {
+                let e = LcNum::constant::<CS>(*e);
+                r.enforce_equal(cs, &e);
+            }
+
+            let bits = vec![
+                a.is_zero(cs)?,
+                a.equals(cs, &b)?,
+                a.equals(cs, &a)?
+            ];
+
+            for (r, e) in bits.iter().zip(self.expected_bits.iter()) {
+                cs.enforce(
+                    LinearCombination::zero() + r.0,
+                    LinearCombination::zero() + CS::one(),
+                    LinearCombination::zero() + (if *e { Fr::one() } else { Fr::zero() }, CS::one())
+                );
+            }
+
+            Ok(MyNumCircuitInput)
+        }
+    }
+
+    for _ in 0..3 {
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        let c: bool = rng.gen();
+
+        let expected = {
+            let mut add = a;
+            add.add_assign(&b);
+            let mut sub = a;
+            sub.sub_assign(&b);
+            let mut scale = a;
+            scale.mul_assign(&Fr::from_str("7").unwrap());
+            let mut mul = a;
+            mul.mul_assign(&b);
+            let mut square = a;
+            square.square();
+            let inverse = a.inverse().unwrap();
+            let mut div = b.inverse().unwrap();
+            div.mul_assign(&a);
+            let select = if c { a } else { b };
+
+            vec![add, sub, scale, mul, square, inverse, div, select]
+        };
+        let expected_bits = vec![false, false, true];
+
+        let params = generate_random_parameters::<Bls12, _, _>(MyNumCircuit {
+            a: Assignment::unknown(),
+            b: Assignment::unknown(),
+            c: Assignment::unknown(),
+            expected: expected.clone(),
+            expected_bits: expected_bits.clone()
+        }, rng).unwrap();
+        let prepared_vk = prepare_verifying_key(&params.vk);
+
+        let circuit = |expected: Vec<Fr>, expected_bits: Vec<bool>| MyNumCircuit {
+            a: Assignment::known(a),
+            b: Assignment::known(b),
+            c: Assignment::known(c),
+            expected: expected,
+            expected_bits: expected_bits
+        };
+
+        let proof = create_random_proof::<Bls12, _, _, _>(circuit(expected.clone(), expected_bits.clone()), &params, rng).unwrap();
+        assert!(verify_proof(&prepared_vk, &proof, |_| Ok(MyNumCircuitInput)).unwrap());
+
+        // A wrong product must not verify.
+        let mut wrong = expected.clone();
+        wrong[3].add_assign(&Fr::one());
+        let params = generate_random_parameters::<Bls12, _, _>(circuit(wrong.clone(), expected_bits.clone()), rng).unwrap();
+        let prepared_vk = prepare_verifying_key(&params.vk);
+        let proof = create_random_proof::<Bls12, _, _, _>(circuit(wrong, expected_bits), &params, rng).unwrap();
+        assert!(!verify_proof(&prepared_vk, &proof, |_| Ok(MyNumCircuitInput)).unwrap());
+    }
+
+    // Zero is detected by is_zero, and cannot be inverted.
+    {
+        struct InverseCircuit(Assignment<Fr>);
+
+        impl Circuit<Bls12> for InverseCircuit {
+            type InputMap = MyNumCircuitInput;
+
+            fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+            {
+                let a = Num::alloc(cs, self.0)?;
+                a.inverse(cs)?;
+
+                Ok(MyNumCircuitInput)
+            }
+        }
+
+        let params = generate_random_parameters::<Bls12, _, _>(InverseCircuit(Assignment::unknown()), rng).unwrap();
+        let prepared_vk = prepare_verifying_key(&params.vk);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(InverseCircuit(Assignment::known(Fr::one())), &params, rng).unwrap();
+        assert!(verify_proof(&prepared_vk, &proof, |_| Ok(MyNumCircuitInput)).unwrap());
+
+        assert!(create_random_proof::<Bls12, _, _, _>(InverseCircuit(Assignment::known(Fr::zero())), &params, rng).is_err());
+
+        struct ZeroCircuit(Assignment<Fr>);
+
+        impl Circuit<Bls12> for ZeroCircuit {
+            type InputMap = MyNumCircuitInput;
+
+            fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+            {
+                let a = Num::alloc(cs, self.0)?;
+                let is_zero = a.is_zero(cs)?;
+
+                cs.enforce(
+                    LinearCombination::zero() + is_zero.0,
+                    LinearCombination::zero() + CS::one(),
+                    LinearCombination::zero() + CS::one()
+                );
+
+                Ok(MyNumCircuitInput)
+            }
+        }
+
+        let params = generate_random_parameters::<Bls12, _, _>(ZeroCircuit(Assignment::unknown()), rng).unwrap();
+        let prepared_vk = prepare_verifying_key(&params.vk);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(ZeroCircuit(Assignment::known(Fr::zero())), &params, rng).unwrap();
+        assert!(verify_proof(&prepared_vk, &proof, |_| Ok(MyNumCircuitInput)).unwrap());
+
+        let proof = create_random_proof::<Bls12, _, _, _>(ZeroCircuit(Assignment::known(Fr::one())), &params, rng).unwrap();
+        assert!(!verify_proof(&prepared_vk, &proof, |_| Ok(MyNumCircuitInput)).unwrap());
+    }
+}
+
+#[test]
+fn test_unpack_modes() {
+    use pairing::bls12_381::{Bls12, Fr};
+
+    let rng = &mut thread_rng();
+
+    #[derive(Copy, Clone)]
+    enum Mode {
+        Strict,
+        NonStrict,
+        Bounded(usize)
+    }
+
+    struct MyUnpackCircuit {
+        value: Assignment<Fr>,
+        mode: Mode
+    }
+
+    impl Circuit<Bls12> for MyUnpackCircuit {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let num = Num::alloc(cs, self.value)?;
+
+            let bits = match self.mode {
+                Mode::Strict => num.unpack_strict(cs)?,
+                Mode::NonStrict => num.unpack_nonstrict(cs)?,
+                Mode::Bounded(n) => num.unpack_bounded(cs, n)?
+            };
+
+            Ok(PublicInputs::new().num(&num).packed_bits(&bits))
+        }
+    }
+
+    let prove = |mode: Mode, value: Fr, rng: &mut ::rand::ThreadRng| {
+        let (params, ivk) = input::generate_input_parameters(MyUnpackCircuit {
+            value: Assignment::unknown(),
+            mode: mode
+        }, rng).unwrap();
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyUnpackCircuit {
+            value: Assignment::known(value),
+            mode: mode
+        }, &params, rng).unwrap();
+
+        let mut bits = BitIterator::new(value.into_repr()).collect::<Vec<_>>();
+        bits.reverse();
+        bits.truncate(match mode {
+            Mode::Bounded(n) => n,
+            _ => Fr::num_bits() as usize
+        });
+
+        let mut inputs = vec![value];
+        inputs.extend(multipack::compute_multipacking::<Bls12>(&bits));
+
+        input::verify_with_inputs(&ivk, &proof, &inputs).unwrap()
+    };
+
+    let value = Fr::rand(rng);
+    assert!(prove(Mode::Strict, value, rng));
+    assert!(prove(Mode::NonStrict, value, rng));
+
+    // 2^64 - 1 fits in 64 bits, 2^64 does not.
+    let mut max = Fr::from_str("18446744073709551615").unwrap();
+    assert!(prove(Mode::Bounded(64), max, rng));
+    max.add_assign(&Fr::one());
+    assert!(!prove(Mode::Bounded(64), max, rng));
+    assert!(prove(Mode::Bounded(65), max, rng));
+}
+
+#[test]
+fn test_packing() {
+    use pairing::bls12_381::{Bls12, Fr};
+
+    let rng = &mut thread_rng();
+
+    struct MyPackingCircuit {
+        bits: Vec<Assignment<bool>>,
+        big_endian: bool,
+        check_canonical: bool
+    }
+
+    impl Circuit<Bls12> for MyPackingCircuit {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut bits = vec![];
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            let num = if self.big_endian {
+                Num::from_bits_be(cs, &bits, self.check_canonical)?
+            } else {
+                Num::from_bits_le(cs, &bits, self.check_canonical)?
+            };
+
+            if self.check_canonical {
+                // Unpacking again must give back the same bits.
+                let unpacked = if self.big_endian {
+                    num.unpack_be(cs)?
+                } else {
+                    num.unpack_le(cs)?
+                };
+
+                for (a, b) in bits.iter().zip(unpacked.iter()) {
+                    LcNum::from_bit(a).enforce_equal(cs, &LcNum::from_bit(b));
+                }
+            }
+
+            Ok(PublicInputs::new().num(&num).bits(&bits))
+        }
+    }
+
+    let num_bits = Fr::num_bits() as usize;
+
+    for &big_endian in &[false, true] {
+        for &check_canonical in &[false, true] {
+            let (params, ivk) = input::generate_input_parameters(MyPackingCircuit {
+                bits: vec![Assignment::unknown(); num_bits],
+                big_endian: big_endian,
+                check_canonical: check_canonical
+            }, rng).unwrap();
+
+            let prove = |repr: <Fr as PrimeField>::Repr, rng: &mut ::rand::ThreadRng| {
+                let mut bits = BitIterator::new(repr).collect::<Vec<_>>();
+                if !big_endian {
+                    bits.reverse();
+                    bits.truncate(num_bits);
+                } else {
+                    bits.drain(0..(256 - num_bits));
+                }
+
+                let proof = create_random_proof::<Bls12, _, _, _>(MyPackingCircuit {
+                    bits: bits.iter().cloned().map(Assignment::known).collect(),
+                    big_endian: big_endian,
+                    check_canonical: check_canonical
+                }, &params, rng).unwrap();
+
+                let mut inputs = vec![Fr::from_repr(repr).unwrap_or(Fr::zero())];
+                inputs.extend(bits.iter().map(|&b| if b { Fr::one() } else { Fr::zero() }));
+
+                input::verify_with_inputs(&ivk, &proof, &inputs).unwrap()
+            };
+
+            let value = Fr::rand(rng);
+            assert!(prove(value.into_repr(), rng));
+
+            // The modulus itself packs into zero, but isn't canonical.
+            assert_eq!(prove(Fr::char(), rng), !check_canonical);
+        }
+    }
+}
+
+impl<E: Engine> Clone for Num<E> {
+    fn clone(&self) -> Num<E> {
+        Num {
+            value: self.value,
+            var: self.var
+        }
+    }
+}
+
+/// A field element represented lazily as a linear combination
+/// of existing variables, along with its assignment. Additions,
+/// subtractions and scaling by constants are free; a variable is
+/// only allocated for the result of a multiplication.
+///
+/// Bellman's `LinearCombination` can't be scaled or inspected,
+/// so the terms are kept here and the linear combination is
+/// built when it is needed in a constraint.
+pub struct LcNum<E: Engine> {
+    value: Assignment<E::Fr>,
+    terms: Vec<(E::Fr, Variable)>
+}
+
+impl<E: Engine> Clone for LcNum<E> {
+    fn clone(&self) -> LcNum<E> {
+        LcNum {
+            value: self.value,
+            terms: self.terms.clone()
+        }
+    }
+}
+
+impl<E: Engine> From<Num<E>> for LcNum<E> {
+    fn from(num: Num<E>) -> LcNum<E> {
+        LcNum {
+            value: num.value,
+            terms: vec![(E::Fr::one(), num.var)]
+        }
+    }
+}
+
+impl<E: Engine> LcNum<E> {
+    pub fn zero() -> LcNum<E> {
+        LcNum {
+            value: Assignment::known(E::Fr::zero()),
+            terms: vec![]
+        }
+    }
+
+    /// A constant, expressed as a multiple of the "one" variable.
+    /// Costs no constraints.
+    pub fn constant<CS: ConstraintSystem<E>>(value: E::Fr) -> LcNum<E> {
+        LcNum {
+            value: Assignment::known(value),
+            terms: vec![(value, CS::one())]
+        }
+    }
+
+    /// The field element a bit represents. Costs no constraints.
+    pub fn from_bit(bit: &Bit) -> LcNum<E> {
+        LcNum {
+            value: match bit.1 {
+                Assignment::Known(true) => Assignment::known(E::Fr::one()),
+                Assignment::Known(false) => Assignment::known(E::Fr::zero()),
+                Assignment::Unknown => Assignment::unknown()
+            },
+            terms: vec![(E::Fr::one(), bit.0)]
+        }
+    }
+
+    /// The field element that little-endian `bits` represent.
+    /// Costs no constraints.
+    pub fn from_bits_le(bits: &[Bit]) -> LcNum<E> {
+        LcNum::pack_le(bits.iter().map(LcNum::from_bit))
+    }
+
+    /// The same as `from_bits_le`, for booleans. Costs no
+    /// constraints.
+    pub fn from_booleans_le<CS: ConstraintSystem<E>>(bits: &[Boolean]) -> LcNum<E> {
+        LcNum::pack_le(bits.iter().map(|b| b.lc::<E, CS>()))
+    }
+
+    /// Sums `nums` weighted by successive powers of two. The terms
+    /// are accumulated in place, so this takes time linear in
+    /// their number.
+    fn pack_le<I: Iterator<Item=LcNum<E>>>(nums: I) -> LcNum<E> {
+        let mut acc = LcNum::zero();
+
+        let mut cur = E::Fr::one();
+        for num in nums {
+            acc.add_assign(&num.scale(cur));
+            cur.double();
+        }
+
+        acc
+    }
+
+    pub fn get_value(&self) -> Assignment<E::Fr> {
+        self.value
+    }
+
+    pub fn lc(&self) -> LinearCombination<E> {
+        self.terms.iter().fold(LinearCombination::zero(), |lc, &(coeff, var)| {
+            lc + (coeff, var)
+        })
+    }
+
+    /// Computes `self + other`. Costs no constraints.
+    pub fn add(&self, other: &LcNum<E>) -> LcNum<E> {
+        let mut result = self.clone();
+        result.add_assign(other);
+
+        result
+    }
+
+    fn add_assign(&mut self, other: &LcNum<E>) {
+        self.terms.extend(other.terms.iter().cloned());
+
+        self.value = match (self.value, other.value) {
+            (Assignment::Known(mut a), Assignment::Known(b)) => {
+                a.add_assign(&b);
+                Assignment::known(a)
+            },
+            _ => Assignment::unknown()
+        };
+    }
+
+    /// Computes `self - other`. Costs no constraints.
+    pub fn sub(&self, other: &LcNum<E>) -> LcNum<E> {
+        let mut neg = E::Fr::one();
+        neg.negate();
+
+        self.add(&other.scale(neg))
+    }
+
+    /// Computes `self * by` for a constant `by`. Costs no
+    /// constraints.
+    pub fn scale(&self, by: E::Fr) -> LcNum<E> {
+        LcNum {
+            value: match self.value {
+                Assignment::Known(mut a) => {
+                    a.mul_assign(&by);
+                    Assignment::known(a)
+                },
+                Assignment::Unknown => Assignment::unknown()
+            },
+            terms: self.terms.iter().map(|&(mut coeff, var)| {
+                coeff.mul_assign(&by);
+                (coeff, var)
+            }).collect()
+        }
+    }
+
+    /// Computes `self * other`. Costs one constraint.
+    pub fn mul<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &LcNum<E>
+    ) -> Result<LcNum<E>, Error>
+    {
+        let mut result_value = Assignment::unknown();
+        let result_var = cs.alloc(|| {
+            let mut e = *self.value.get()?;
+            e.mul_assign(other.value.get()?);
+
+            result_value = Assignment::known(e);
+
+            Ok(e)
+        })?;
+
         cs.enforce(
-            LinearCombination::zero() + CS::one() - var,
-            LinearCombination::zero() + var,
-            LinearCombination::zero()
+            self.lc(),
+            other.lc(),
+            LinearCombination::zero() + result_var
         );
 
-        Ok(Bit(var, value))
+        Ok(Num {
+            value: result_value,
+            var: result_var
+        }.into())
     }
 
-    fn and<E, CS>(&self, cs: &mut CS, other: &Bit) -> Result<Bit, Error>
-        where E: Engine, CS: ConstraintSystem<E>
+    /// Computes `self^2`. Costs one constraint.
+    pub fn square<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<LcNum<E>, Error>
     {
-        match (*self, *other) {
-            (Bit(a_var, a_val), Bit(b_var, b_val)) => {
-                let mut c_val = Assignment::unknown();
-
-                let c_var = cs.alloc(|| {
-                    if *a_val.get()? && *b_val.get()? {
-                        c_val = Assignment::known(true);
-                        Ok(E::Fr::one())
-                    } else {
-                        c_val = Assignment::known(false);
-                        Ok(E::Fr::zero())
-                    }
-                })?;
-
-                cs.enforce(
-                    LinearCombination::zero() + a_var,
-                    LinearCombination::zero() + b_var,
-                    LinearCombination::zero() + c_var
-                );
-
-                Ok(Bit(c_var, c_val))
-            }
-        }
+        self.mul(cs, self)
     }
-}
 
-pub struct Num<E: Engine> {
-    value: Assignment<E::Fr>,
-    var: Variable
-}
+    /// Computes `self^n` for `n > 0`, by square-and-multiply.
+    /// Costs a constraint per squaring and multiplication, so
+    /// three for `n = 5`.
+    pub fn pow<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        mut n: u64
+    ) -> Result<LcNum<E>, Error>
+    {
+        assert!(n > 0);
 
-fn assignment_into_bits<E: Engine, CS: ConstraintSystem<E>>(num: &Assignment<E::Fr>, cs: &mut CS) -> Result<Vec<Bit>, Error>
-{
-    Ok(match num.get() {
-        Ok(num) => {
-            let mut res_assignment = vec![];
-            for b in BitIterator::new(num.into_repr()) {
-                res_assignment.push(Assignment::known(b));
-            }
-            res_assignment.reverse();
-            res_assignment.truncate(E::Fr::num_bits() as usize);
+        let mut base = self.clone();
+        let mut acc: Option<LcNum<E>> = None;
 
-            let mut res_bits = vec![];
-            for b in res_assignment {
-                res_bits.push(Bit::alloc(cs, b)?);
+        loop {
+            if n & 1 == 1 {
+                acc = Some(match acc {
+                    Some(acc) => acc.mul(cs, &base)?,
+                    None => base.clone()
+                });
             }
-            res_bits
-        },
-        Err(_) => {
-            let mut res_bits = vec![];
 
-            for _ in 0..E::Fr::num_bits() {
-                res_bits.push(Bit::alloc(cs, Assignment::unknown())?);
+            n >>= 1;
+            if n == 0 {
+                break;
             }
 
-            res_bits
+            base = base.square(cs)?;
         }
-    })
-}
 
-impl<E: Engine> Num<E> {
-    pub fn unpack<CS: ConstraintSystem<E>>(
+        Ok(acc.unwrap())
+    }
+
+    /// Computes `self / other`. Costs one constraint, with the
+    /// same caveats as `Num::div`.
+    pub fn div<CS: ConstraintSystem<E>>(
         &self,
-        cs: &mut CS
-    ) -> Result<Vec<Bit>, Error>
+        cs: &mut CS,
+        other: &LcNum<E>
+    ) -> Result<Num<E>, Error>
     {
-        let bits = assignment_into_bits(&self.value, cs)?;
-
-        let mut lc = LinearCombination::zero();
+        let mut result_value = Assignment::unknown();
+        let result_var = cs.alloc(|| {
+            let mut e = other.value.get()?.inverse().ok_or(Error::AssignmentMissing)?;
+            e.mul_assign(self.value.get()?);
 
-        let mut cur = E::Fr::one();
-        let two = E::Fr::from_str("2").unwrap();
-        for b in &bits {
-            lc = lc + (cur, b.0);
-            cur.mul_assign(&two);
-        }
+            result_value = Assignment::known(e);
 
-        lc = lc - self.var;
+            Ok(e)
+        })?;
 
         cs.enforce(
-            LinearCombination::zero(),
-            LinearCombination::zero(),
-            lc
+            LinearCombination::zero() + result_var,
+            other.lc(),
+            self.lc()
         );
 
-        assert_less_than_r(&bits, cs)?;
-
-        Ok(bits)
+        Ok(Num {
+            value: result_value,
+            var: result_var
+        })
     }
 
-    fn mul<CS: ConstraintSystem<E>>(
-        &self,
+    /// Returns `a` if `condition` is set, and `b` otherwise.
+    /// Costs one constraint.
+    pub fn conditionally_select<CS: ConstraintSystem<E>>(
         cs: &mut CS,
-        other: &Num<E>
+        condition: &Bit,
+        a: &LcNum<E>,
+        b: &LcNum<E>
     ) -> Result<Num<E>, Error>
     {
         let mut result_value = Assignment::unknown();
         let result_var = cs.alloc(|| {
-            let mut e = *self.value.get()?;
-            e.mul_assign(other.value.get()?);
+            let e = if *condition.1.get()? {
+                *a.value.get()?
+            } else {
+                *b.value.get()?
+            };
 
             result_value = Assignment::known(e);
 
             Ok(e)
         })?;
 
+        // Constrain: (a - b) * condition = result - b
         cs.enforce(
-            LinearCombination::zero() + self.var,
-            LinearCombination::zero() + other.var,
-            LinearCombination::zero() + result_var
+            a.sub(b).lc(),
+            LinearCombination::zero() + condition.0,
+            LinearCombination::zero() + result_var - &b.lc()
         );
 
         Ok(Num {
@@ -235,17 +1369,162 @@ impl<E: Engine> Num<E> {
             var: result_var
         })
     }
+
+    /// The same as `Num::swap_if`, for linear combinations.
+    /// Costs one constraint.
+    pub fn swap_if<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        condition: &Bit,
+        a: &LcNum<E>,
+        b: &LcNum<E>
+    ) -> Result<(Num<E>, LcNum<E>), Error>
+    {
+        let first = LcNum::conditionally_select(cs, condition, b, a)?;
+        let second = a.add(b).sub(&LcNum::from(first.clone()));
+
+        Ok((first, second))
+    }
+
+    /// Enforces `self = other`. Costs one constraint.
+    pub fn enforce_equal<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        other: &LcNum<E>
+    )
+    {
+        cs.enforce(
+            self.sub(other).lc(),
+            LinearCombination::zero() + CS::one(),
+            LinearCombination::zero()
+        );
+    }
+
+    /// Allocates `num_bits` little-endian bits and enforces that
+    /// they pack into this number.
+    fn unpack_into<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        num_bits: usize
+    ) -> Result<Vec<Bit>, Error>
+    {
+        let bits = assignment_into_bits(&self.value, num_bits, cs)?;
+
+        let lc = LcNum::from_bits_le(&bits).lc() - &self.lc();
+
+        cs.enforce(
+            LinearCombination::zero(),
+            LinearCombination::zero(),
+            lc
+        );
+
+        Ok(bits)
+    }
+
+    /// Same as `Num::unpack_strict`.
+    pub fn unpack_strict<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Vec<Bit>, Error>
+    {
+        let bits = self.unpack_into(cs, E::Fr::num_bits() as usize)?;
+
+        assert_less_than_r(&bits, cs)?;
+
+        Ok(bits)
+    }
+
+    /// Same as `Num::unpack_bounded`.
+    pub fn unpack_bounded<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        num_bits: usize
+    ) -> Result<Vec<Bit>, Error>
+    {
+        assert!(num_bits <= E::Fr::capacity() as usize);
+
+        self.unpack_into(cs, num_bits)
+    }
+
+    /// Exposes this linear combination as a public input. Costs
+    /// one constraint.
+    pub fn inputize<CS: PublicConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<(), Error>
+    {
+        let input = Num::alloc_input(cs, self.value)?;
+
+        self.enforce_equal(cs, &input.into());
+
+        Ok(())
+    }
+
+    /// Allocates a variable holding this linear combination, for
+    /// gadgets that need a `Num`. Costs one constraint.
+    pub fn into_num<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS
+    ) -> Result<Num<E>, Error>
+    {
+        let result = Num::alloc(cs, self.value)?;
+
+        self.enforce_equal(cs, &result.clone().into());
+
+        Ok(result)
+    }
 }
 
-impl<E: Engine> Clone for Num<E> {
-    fn clone(&self) -> Num<E> {
-        Num {
-            value: self.value,
-            var: self.var
+/// Wraps a constraint system, counting the variables and
+/// constraints synthesized through it. Useful for measuring
+/// the cost of gadgets.
+pub struct ConstraintCounter<'a, E: Engine, CS: ConstraintSystem<E> + 'a> {
+    cs: &'a mut CS,
+    pub num_aux: usize,
+    pub num_inputs: usize,
+    pub num_constraints: usize,
+    _marker: ::std::marker::PhantomData<E>
+}
+
+impl<'a, E: Engine, CS: ConstraintSystem<E> + 'a> ConstraintCounter<'a, E, CS> {
+    pub fn new(cs: &'a mut CS) -> Self {
+        ConstraintCounter {
+            cs: cs,
+            num_aux: 0,
+            num_inputs: 0,
+            num_constraints: 0,
+            _marker: ::std::marker::PhantomData
         }
     }
 }
 
+impl<'a, E: Engine, CS: ConstraintSystem<E> + 'a> ConstraintSystem<E> for ConstraintCounter<'a, E, CS> {
+    fn alloc<F: FnOnce() -> Result<E::Fr, Error>>(&mut self, f: F) -> Result<Variable, Error> {
+        self.num_aux += 1;
+
+        self.cs.alloc(f)
+    }
+
+    fn enforce(
+        &mut self,
+        a: LinearCombination<E>,
+        b: LinearCombination<E>,
+        c: LinearCombination<E>
+    )
+    {
+        self.num_constraints += 1;
+
+        self.cs.enforce(a, b, c)
+    }
+}
+
+impl<'a, E: Engine, CS: PublicConstraintSystem<E> + 'a> PublicConstraintSystem<E> for ConstraintCounter<'a, E, CS> {
+    fn alloc_input<F: FnOnce() -> Result<E::Fr, Error>>(&mut self, f: F) -> Result<Variable, Error> {
+        self.num_inputs += 1;
+
+        self.cs.alloc_input(f)
+    }
+}
+
 fn coordinate_lookup<E: Engine, CS: ConstraintSystem<E>>(
     cs: &mut CS,
     table: &[E::Fr],
@@ -383,40 +1662,8 @@ fn test_lookup() {
         }
     }
 
-    struct MyLookupCircuitInput<E: Engine> {
-        x: Num<E>,
-        y: Num<E>
-    }
-
-    impl<E: Engine> Input<E> for MyLookupCircuitInput<E> {
-        fn synthesize<CS: PublicConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), Error>
-        {
-            let x_input = cs.alloc_input(|| {
-                Ok(*self.x.value.get()?)
-            })?;
-
-            let y_input = cs.alloc_input(|| {
-                Ok(*self.y.value.get()?)
-            })?;
-
-            cs.enforce(
-                LinearCombination::zero() + self.x.var,
-                LinearCombination::zero() + CS::one(),
-                LinearCombination::zero() + x_input
-            );
-
-            cs.enforce(
-                LinearCombination::zero() + self.y.var,
-                LinearCombination::zero() + CS::one(),
-                LinearCombination::zero() + y_input
-            );
-
-            Ok(())
-        }
-    }
-
     impl<'a> Circuit<Bls12> for MyLookupCircuit<'a> {
-        type InputMap = MyLookupCircuitInput<Bls12>;
+        type InputMap = PublicInputs<Bls12>;
 
         fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
         {
@@ -429,30 +1676,87 @@ fn test_lookup() {
 
             let (x, y) = point_lookup(cs, self.x_table, self.y_table, &bits)?;
 
-            Ok(MyLookupCircuitInput {
-                x: x,
-                y: y
-            })
+            Ok(PublicInputs::new().num(&x).num(&y))
         }
     }
 
-    let params = generate_random_parameters::<Bls12, _, _>(MyLookupCircuit::blank(&x_table, &y_table), rng).unwrap();
-
-    let prepared_vk = prepare_verifying_key(&params.vk);
+    let (params, ivk) = input::generate_input_parameters(MyLookupCircuit::blank(&x_table, &y_table), rng).unwrap();
 
     for i in 0..16 {
         let proof = create_random_proof::<Bls12, _, _, _>(MyLookupCircuit::new(
             i & (1 << 0) != 0, i & (1 << 1) != 0, i & (1 << 2) != 0, i & (1 << 3) != 0, &x_table, &y_table), &params, rng).unwrap();
 
-        assert!(verify_proof(&prepared_vk, &proof, |cs| {
-            let x_var = cs.alloc(|| Ok(x_table[i]))?;
-            let y_var = cs.alloc(|| Ok(y_table[i]))?;
+        assert!(input::verify_with_inputs(&ivk, &proof, &[x_table[i], y_table[i]]).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[y_table[i], x_table[i]]).unwrap());
+    }
+}
+
+#[test]
+fn test_swap_and_multiplex() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
 
-            Ok(MyLookupCircuitInput {
-                x: Num { var: x_var, value: Assignment::known(x_table[i]) },
-                y: Num { var: y_var, value: Assignment::known(y_table[i]) }
-            })
-        }).unwrap());
+    struct MyMultiplexCircuit<'a> {
+        table: Vec<Assignment<Fr>>,
+        bits: Vec<Assignment<bool>>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyMultiplexCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut table = vec![];
+            for v in self.table {
+                table.push(Num::alloc(cs, v)?);
+            }
+
+            let mut bits = vec![];
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let selected = Num::multiplex(&mut counter, &bits, &table)?;
+            let (first, second) = Num::swap_if(&mut counter, &bits[0], &table[0], &table[1])?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(&selected).num(&first).num(&second))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    // A short table is padded with its last entry.
+    for &size in &[8, 5] {
+        let (params, ivk) = input::generate_input_parameters(MyMultiplexCircuit {
+            table: vec![Assignment::unknown(); size],
+            bits: vec![Assignment::unknown(); 3],
+            num_constraints: &num_constraints
+        }, rng).unwrap();
+
+        assert_eq!(num_constraints.get(), (size - 1) + 1);
+
+        let table = (0..size).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+
+        for i in 0..8 {
+            let proof = create_random_proof::<Bls12, _, _, _>(MyMultiplexCircuit {
+                table: table.iter().map(|&v| Assignment::known(v)).collect(),
+                bits: (0..3).map(|b| Assignment::known(i & (1 << b) != 0)).collect(),
+                num_constraints: &num_constraints
+            }, &params, rng).unwrap();
+
+            let selected = table[::std::cmp::min(i, size - 1)];
+            let (first, second) = if i & 1 != 0 { (table[1], table[0]) } else { (table[0], table[1]) };
+
+            assert!(input::verify_with_inputs(&ivk, &proof, &[selected, first, second]).unwrap());
+            assert!(!input::verify_with_inputs(&ivk, &proof, &[Fr::rand(rng), first, second]).unwrap());
+            assert!(!input::verify_with_inputs(&ivk, &proof, &[selected, second, first]).unwrap());
+        }
     }
 }
 
@@ -510,25 +1814,88 @@ impl Point {
                     y: y
                 };
 
-                assert!(tmp.is_on_curve(j)); 
-                tmp.double(j);
-                tmp.double(j);
-                tmp.double(j);
+                assert!(tmp.is_on_curve(j)); 
+                tmp.double(j);
+                tmp.double(j);
+                tmp.double(j);
+
+                // let mut tmp2 = tmp;
+                // tmp2.mul_assign(&Fr::from_str("6554484396890773809930967563523245729705921265872317281365359162392183254199").unwrap(), j);
+                // assert!(tmp2 == Point::zero());
+
+                return tmp;
+            }
+        }
+    }
+
+    pub fn zero() -> Point {
+        Point {
+            x: Fr::zero(),
+            y: Fr::one()
+        }
+    }
+
+    /// Finds the x-coordinate for `y` whose lowest bit is `sign`,
+    /// if there is one.
+    pub fn recover_x(y: Fr, sign: bool, j: &JubJub) -> Option<Fr> {
+        // x^2 = (y^2 - 1) / (d y^2 + 1)
+        let mut y2 = y;
+        y2.square();
+
+        let mut n = y2;
+        n.sub_assign(&Fr::one());
+
+        let mut d = y2;
+        d.mul_assign(&j.d);
+        d.add_assign(&Fr::one());
+        n.mul_assign(&d.inverse()?);
+
+        let mut x = n.sqrt()?;
+
+        if x.into_repr().is_odd() != sign {
+            x.negate();
+        }
+
+        if x.into_repr().is_odd() != sign {
+            // x is zero, which has no odd square root.
+            return None;
+        }
+
+        Some(x)
+    }
+
+    /// The compressed representation: the 255 little-endian bits
+    /// of y, then the lowest bit of x.
+    pub fn repr_bits(&self) -> Vec<bool> {
+        let mut bits = BitIterator::new(self.y.into_repr()).collect::<Vec<_>>();
+        bits.reverse();
+        bits.truncate(255);
+        bits.push(self.x.into_repr().is_odd());
+
+        bits
+    }
+
+    /// Decompresses a point from `repr_bits`, returning `None` if
+    /// y isn't canonical or no point has that y and sign.
+    pub fn from_compressed_bits(bits: &[bool], j: &JubJub) -> Option<Point> {
+        assert_eq!(bits.len(), 256);
 
-                // let mut tmp2 = tmp;
-                // tmp2.mul_assign(&Fr::from_str("6554484396890773809930967563523245729705921265872317281365359162392183254199").unwrap(), j);
-                // assert!(tmp2 == Point::zero());
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        for &b in bits[0..255].iter().rev() {
+            repr.mul2();
 
-                return tmp;
+            if b {
+                repr.as_mut()[0] |= 1;
             }
         }
-    }
 
-    pub fn zero() -> Point {
-        Point {
-            x: Fr::zero(),
-            y: Fr::one()
-        }
+        let y = Fr::from_repr(repr).ok()?;
+        let x = Point::recover_x(y, bits[255], j)?;
+
+        Some(Point {
+            x: x,
+            y: y
+        })
     }
 
     pub fn is_on_curve(&self, j: &JubJub) -> bool {
@@ -584,6 +1951,10 @@ impl Point {
         self.y = y;
     }
 
+    pub fn negate(&mut self) {
+        self.x.negate();
+    }
+
     pub fn double(&mut self, j: &JubJub) {
         let tmp = *self;
 
@@ -635,6 +2006,11 @@ pub fn generate_constant_table<R>(rng: &mut R, j: &JubJub)
     }).collect::<Vec<_>>()
 }
 
+/// Hashes 512 bits with 128 windowed lookups into `generators`,
+/// returning the y-coordinate of the sum of the selected points.
+///
+/// Each lookup costs 6 constraints and each of the 127 additions
+/// costs 6, except for the last which skips x: 1529 in total.
 pub fn pedersen_hash<CS>(
     cs: &mut CS,
     bits: &[Bit],
@@ -652,82 +2028,58 @@ pub fn pedersen_hash<CS>(
         assert_eq!(x_table.len(), 16);
         assert_eq!(y_table.len(), 16);
 
-        lookups.push(point_lookup(cs, x_table, y_table, fourbits)?);
+        let (x, y) = point_lookup(cs, x_table, y_table, fourbits)?;
+        lookups.push(EdwardsPoint::interpret_unchecked(x, y));
     }
 
     assert_eq!(lookups.len(), generators.len());
 
-    let mut cur_x = lookups[0].0.clone();
-    let mut cur_y = lookups[0].1.clone();
+    let mut lookups = lookups.into_iter();
 
-    for (i, (next_x, next_y)) in lookups.into_iter().skip(1).enumerate() {
-        let x1y2 = cur_x.mul(cs, &next_y)?;
-        let y1x2 = cur_y.mul(cs, &next_x)?;
-        let y1y2 = cur_y.mul(cs, &next_y)?;
-        let x1x2 = cur_x.mul(cs, &next_x)?;
-        let tau = y1y2.mul(cs, &x1x2)?;
+    let mut cur = lookups.next().unwrap();
+    let last = lookups.next_back().unwrap();
 
-        // We don't need to compute x for the last
-        // one.
-        if i != (generators.len() - 1) {
-            let mut x3_val = Assignment::unknown();
-            let x3 = cs.alloc(|| {
-                let mut numerator = *x1y2.value.get()?;
-                numerator.add_assign(y1x2.value.get()?);
-
-                let mut denominator = *tau.value.get()?;
-                denominator.mul_assign(&j.d);
-                denominator.add_assign(&Fr::one());
+    for next in lookups {
+        cur = cur.add(cs, &next, j)?;
+    }
 
-                numerator.mul_assign(&denominator.inverse().unwrap());
+    // We don't need to compute x for the last
+    // one.
+    cur.add_get_y(cs, &last, j)
+}
 
-                x3_val = Assignment::known(numerator);
+/// The native equivalent of `pedersen_hash`, for computing
+/// witnesses and expected outputs.
+pub fn pedersen_hash_native(
+    bits: &[bool],
+    generators: &[(Vec<Fr>, Vec<Fr>)],
+    j: &JubJub
+) -> Fr
+{
+    assert_eq!(bits.len(), 512);
+    assert_eq!(generators.len(), (512/4));
 
-                Ok(numerator)
-            })?;
+    let mut cur = Point::zero();
 
-            cs.enforce(
-                LinearCombination::zero() + CS::one() + (j.d, tau.var),
-                LinearCombination::zero() + x3,
-                LinearCombination::zero() + x1y2.var + y1x2.var
-            );
+    for (chunk, g) in bits.chunks(4).zip(generators.iter()) {
+        let mut idx = 0;
+        for c in chunk.iter().rev() {
+            idx <<= 1;
 
-            cur_x = Num {
-                value: x3_val,
-                var: x3
-            };
+            if *c {
+                idx |= 1;
+            }
         }
 
-        let mut y3_val = Assignment::unknown();
-        let y3 = cs.alloc(|| {
-            let mut numerator = *x1x2.value.get()?;
-            numerator.add_assign(y1y2.value.get()?);
-
-            let mut denominator = *tau.value.get()?;
-            denominator.mul_assign(&j.d);
-            denominator.negate();
-            denominator.add_assign(&Fr::one());
-
-            numerator.mul_assign(&denominator.inverse().unwrap());
-
-            y3_val = Assignment::known(numerator);
-
-            Ok(numerator)
-        })?;
-
-        cs.enforce(
-            LinearCombination::zero() + CS::one() - (j.d, tau.var),
-            LinearCombination::zero() + y3,
-            LinearCombination::zero() + x1x2.var + y1y2.var
-        );
-
-        cur_y = Num {
-            value: y3_val,
-            var: y3
+        let new_point = Point {
+            x: g.0[idx],
+            y: g.1[idx]
         };
+
+        cur.add_assign(&new_point, j);
     }
 
-    Ok(cur_y)
+    cur.y
 }
 
 #[test]
@@ -768,29 +2120,8 @@ fn test_pedersen() {
         }
     }
 
-    struct MyLookupCircuitInput<E: Engine> {
-        r: Num<E>
-    }
-
-    impl<E: Engine> Input<E> for MyLookupCircuitInput<E> {
-        fn synthesize<CS: PublicConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), Error>
-        {
-            let r_input = cs.alloc_input(|| {
-                Ok(*self.r.value.get()?)
-            })?;
-
-            cs.enforce(
-                LinearCombination::zero() + self.r.var,
-                LinearCombination::zero() + CS::one(),
-                LinearCombination::zero() + r_input
-            );
-
-            Ok(())
-        }
-    }
-
     impl<'a> Circuit<Bls12> for MyLookupCircuit<'a> {
-        type InputMap = MyLookupCircuitInput<Bls12>;
+        type InputMap = PublicInputs<Bls12>;
 
         fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
         {
@@ -801,16 +2132,51 @@ fn test_pedersen() {
 
             let res = pedersen_hash(cs, &bits, self.generators, self.j)?;
 
-            Ok(MyLookupCircuitInput {
-                r: res
-            })
+            Ok(PublicInputs::new().num(&res))
         }
     }
 
     let j = JubJub::new();
     let generators = generate_constant_table(rng, &j);
-    let params = generate_random_parameters::<Bls12, _, _>(MyLookupCircuit::blank(&generators, &j), rng).unwrap();
-    let prepared_vk = prepare_verifying_key(&params.vk);
+    let (params, ivk) = input::generate_input_parameters(MyLookupCircuit::blank(&generators, &j), rng).unwrap();
+
+    // Measure the hash on its own, without the bit allocations.
+    {
+        struct CountingCircuit<'a> {
+            generators: &'a [(Vec<Fr>, Vec<Fr>)],
+            j: &'a JubJub,
+            num_constraints: &'a ::std::cell::Cell<usize>
+        }
+
+        impl<'a> Circuit<Bls12> for CountingCircuit<'a> {
+            type InputMap = PublicInputs<Bls12>;
+
+            fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+            {
+                let mut bits = Vec::with_capacity(512);
+                for _ in 0..512 {
+                    bits.push(Bit::alloc(cs, Assignment::unknown())?);
+                }
+
+                let mut counter = ConstraintCounter::new(cs);
+                let res = pedersen_hash(&mut counter, &bits, self.generators, self.j)?;
+                self.num_constraints.set(counter.num_constraints);
+
+                Ok(PublicInputs::new().num(&res))
+            }
+        }
+
+        let num_constraints = ::std::cell::Cell::new(0);
+        generate_random_parameters::<Bls12, _, _>(CountingCircuit {
+            generators: &generators,
+            j: &j,
+            num_constraints: &num_constraints
+        }, rng).unwrap();
+
+        // Previously 1657: each addition allocated x1x2 and y1y2
+        // separately, and the last x was always computed.
+        assert_eq!(num_constraints.get(), 1529);
+    }
 
     let bits = (0..512).map(|_| rng.gen()).collect::<Vec<bool>>();
 
@@ -820,65 +2186,70 @@ fn test_pedersen() {
         &j
     ), &params, rng).unwrap();
 
-    let expected_result = {
-        let mut cur = Point::zero();
-
-        for (chunk, g) in bits.chunks(4).zip(generators.iter()) {
-            let mut idx = 0;
-            for c in chunk.iter().rev() {
-                idx <<= 1;
-
-                if *c {
-                    idx |= 1;
-                }
-            }
-
-            let new_point = Point {
-                x: g.0[idx],
-                y: g.1[idx]
-            };
-
-            cur.add_assign(&new_point, &j);
-        }
+    let expected_result = pedersen_hash_native(&bits, &generators, &j);
 
-        cur.y
-    };
+    assert!(input::verify_with_inputs(&ivk, &proof, &[expected_result]).unwrap());
 
-    assert!(verify_proof(&prepared_vk, &proof, |cs| {
-        let r_var = cs.alloc(|| Ok(expected_result))?;
+    let mut wrong_result = expected_result;
+    wrong_result.add_assign(&Fr::one());
 
-        Ok(MyLookupCircuitInput {
-            r: Num { var: r_var, value: Assignment::known(expected_result) }
-        })
-    }).unwrap());
+    assert!(!input::verify_with_inputs(&ivk, &proof, &[wrong_result]).unwrap());
 }
 
-/// A boolean object that's rather fun! (way too tired for this)
+/// A boolean which may be a constant, a bit, or the negation of a
+/// bit. Negation and operations involving constants are free.
 #[derive(Clone, Copy)]
-enum FunBit {
+pub enum Boolean {
     Constant(bool),
     Is(Variable, Assignment<bool>),
     Not(Variable, Assignment<bool>)
 }
 
-impl FunBit {
-    fn from_bit(b: Bit) -> FunBit {
-        FunBit::Is(b.0, b.1)
+impl Boolean {
+    pub fn from_bit(b: Bit) -> Boolean {
+        Boolean::Is(b.0, b.1)
+    }
+
+    pub fn get_value(&self) -> Assignment<bool> {
+        match *self {
+            Boolean::Constant(b) => Assignment::known(b),
+            Boolean::Is(_, Assignment::Known(b)) => Assignment::known(b),
+            Boolean::Not(_, Assignment::Known(b)) => Assignment::known(!b),
+            _ => Assignment::unknown()
+        }
+    }
+
+    /// The field element this boolean represents. Costs no
+    /// constraints.
+    pub fn lc<E: Engine, CS: ConstraintSystem<E>>(&self) -> LcNum<E> {
+        let one = LcNum::constant::<CS>(E::Fr::one());
+
+        match *self {
+            Boolean::Constant(true) => one,
+            Boolean::Constant(false) => LcNum::zero(),
+            Boolean::Is(var, val) => LcNum::from_bit(&Bit(var, val)),
+            Boolean::Not(var, val) => one.sub(&LcNum::from_bit(&Bit(var, val)))
+        }
+    }
+
+    pub fn assert_is_true<E: Engine, CS: ConstraintSystem<E>>(&self, cs: &mut CS)
+    {
+        self.not().assert_is_false(cs)
     }
 
-    fn assert_is_false<E: Engine, CS: ConstraintSystem<E>>(&self, cs: &mut CS)
+    pub fn assert_is_false<E: Engine, CS: ConstraintSystem<E>>(&self, cs: &mut CS)
     {
         match *self {
-            FunBit::Constant(false) => {},
-            FunBit::Constant(true) => panic!("is false when shouldn't be"),
-            FunBit::Is(is_var, _) => {
+            Boolean::Constant(false) => {},
+            Boolean::Constant(true) => panic!("is false when shouldn't be"),
+            Boolean::Is(is_var, _) => {
                 cs.enforce(
                     LinearCombination::zero() + is_var,
                     LinearCombination::zero() + CS::one(),
                     LinearCombination::zero()
                 );
             },
-            FunBit::Not(not_var, _) => {
+            Boolean::Not(not_var, _) => {
                 cs.enforce(
                     LinearCombination::zero() + CS::one() - not_var,
                     LinearCombination::zero() + CS::one(),
@@ -888,32 +2259,32 @@ impl FunBit {
         }
     }
 
-    fn not(&self) -> FunBit {
+    pub fn not(&self) -> Boolean {
         match *self {
-            FunBit::Constant(b) => FunBit::Constant(!b),
-            FunBit::Is(var, val) => FunBit::Not(var, val),
-            FunBit::Not(var, val) => FunBit::Is(var, val)
+            Boolean::Constant(b) => Boolean::Constant(!b),
+            Boolean::Is(var, val) => Boolean::Not(var, val),
+            Boolean::Not(var, val) => Boolean::Is(var, val)
         }
     }
 
-    fn or<E: Engine, CS: ConstraintSystem<E>>(&self, other: &FunBit, cs: &mut CS)
-        -> Result<FunBit, Error>
+    pub fn or<E: Engine, CS: ConstraintSystem<E>>(&self, other: &Boolean, cs: &mut CS)
+        -> Result<Boolean, Error>
     {
         Ok(self.not().and(&other.not(), cs)?.not())
     }
 
-    fn xor<E: Engine, CS: ConstraintSystem<E>>(&self, other: &FunBit, cs: &mut CS)
-        -> Result<FunBit, Error>
+    pub fn xor<E: Engine, CS: ConstraintSystem<E>>(&self, other: &Boolean, cs: &mut CS)
+        -> Result<Boolean, Error>
     {
         Ok(match (*self, *other) {
-            (FunBit::Constant(false), a) | (a, FunBit::Constant(false)) => {
+            (Boolean::Constant(false), a) | (a, Boolean::Constant(false)) => {
                 a
             },
-            (FunBit::Constant(true), a) | (a, FunBit::Constant(true)) => {
+            (Boolean::Constant(true), a) | (a, Boolean::Constant(true)) => {
                 a.not()
             },
-            (FunBit::Is(a_var, a_val), FunBit::Is(b_var, b_val)) |
-            (FunBit::Not(a_var, a_val), FunBit::Not(b_var, b_val)) => {
+            (Boolean::Is(a_var, a_val), Boolean::Is(b_var, b_val)) |
+            (Boolean::Not(a_var, a_val), Boolean::Not(b_var, b_val)) => {
                 let mut c_val = Assignment::unknown();
 
                 let c_var = cs.alloc(|| {
@@ -934,26 +2305,26 @@ impl FunBit {
                     LinearCombination::zero() + a_var + b_var - c_var
                 );
 
-                FunBit::Is(c_var, c_val)
+                Boolean::Is(c_var, c_val)
             }
-            (FunBit::Is(is_var, is_val), FunBit::Not(not_var, not_val)) |
-            (FunBit::Not(not_var, not_val), FunBit::Is(is_var, is_val)) => {
-                FunBit::Is(is_var, is_val).xor(&FunBit::Is(not_var, not_val), cs)?.not()
+            (Boolean::Is(is_var, is_val), Boolean::Not(not_var, not_val)) |
+            (Boolean::Not(not_var, not_val), Boolean::Is(is_var, is_val)) => {
+                Boolean::Is(is_var, is_val).xor(&Boolean::Is(not_var, not_val), cs)?.not()
             }
         })
     }
 
-    fn and<E: Engine, CS: ConstraintSystem<E>>(&self, other: &FunBit, cs: &mut CS)
-        -> Result<FunBit, Error>
+    pub fn and<E: Engine, CS: ConstraintSystem<E>>(&self, other: &Boolean, cs: &mut CS)
+        -> Result<Boolean, Error>
     {
         Ok(match (*self, *other) {
-            (FunBit::Constant(false), _) | (_, FunBit::Constant(false)) => {
-                FunBit::Constant(false)
+            (Boolean::Constant(false), _) | (_, Boolean::Constant(false)) => {
+                Boolean::Constant(false)
             },
-            (FunBit::Constant(true), a) | (a, FunBit::Constant(true)) => {
+            (Boolean::Constant(true), a) | (a, Boolean::Constant(true)) => {
                 a
             },
-            (FunBit::Is(a_var, a_val), FunBit::Is(b_var, b_val)) => {
+            (Boolean::Is(a_var, a_val), Boolean::Is(b_var, b_val)) => {
                 let mut c_val = Assignment::unknown();
 
                 let c_var = cs.alloc(|| {
@@ -972,9 +2343,9 @@ impl FunBit {
                     LinearCombination::zero() + c_var
                 );
 
-                FunBit::Is(c_var, c_val)
+                Boolean::Is(c_var, c_val)
             },
-            (FunBit::Not(a_var, a_val), FunBit::Not(b_var, b_val)) => {
+            (Boolean::Not(a_var, a_val), Boolean::Not(b_var, b_val)) => {
                 let mut c_val = Assignment::unknown();
 
                 let c_var = cs.alloc(|| {
@@ -993,10 +2364,10 @@ impl FunBit {
                     LinearCombination::zero() + c_var
                 );
 
-                FunBit::Is(c_var, c_val)
+                Boolean::Is(c_var, c_val)
             },
-            (FunBit::Is(is_var, is_val), FunBit::Not(not_var, not_val)) |
-            (FunBit::Not(not_var, not_val), FunBit::Is(is_var, is_val)) => {
+            (Boolean::Is(is_var, is_val), Boolean::Not(not_var, not_val)) |
+            (Boolean::Not(not_var, not_val), Boolean::Is(is_var, is_val)) => {
                 let mut c_val = Assignment::unknown();
 
                 let c_var = cs.alloc(|| {
@@ -1015,41 +2386,360 @@ impl FunBit {
                     LinearCombination::zero() + c_var
                 );
 
-                FunBit::Is(c_var, c_val)
+                Boolean::Is(c_var, c_val)
             }
         })
     }
+
+    /// Allocates the boolean with the value `value`, which
+    /// `enforce` must constrain. Costs no constraints.
+    fn alloc_determined<E, CS, F>(cs: &mut CS, value: F) -> Result<Bit, Error>
+        where E: Engine, CS: ConstraintSystem<E>, F: FnOnce() -> Result<bool, Error>
+    {
+        let mut val = Assignment::unknown();
+        let var = cs.alloc(|| {
+            let v = value()?;
+            val = Assignment::known(v);
+
+            if v { Ok(E::Fr::one()) } else { Ok(E::Fr::zero()) }
+        })?;
+
+        Ok(Bit(var, val))
+    }
+
+    /// Computes SHA-256's choose, `(a & b) ^ (!a & c)`, as
+    /// `c + a * (b - c)`. Costs one constraint, and none when `a`
+    /// or both of `b` and `c` are constant.
+    pub fn sha256_ch<E: Engine, CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        a: &Boolean,
+        b: &Boolean,
+        c: &Boolean
+    ) -> Result<Boolean, Error>
+    {
+        match (*a, *b, *c) {
+            (Boolean::Constant(true), b, _) => return Ok(b),
+            (Boolean::Constant(false), _, c) => return Ok(c),
+            (_, Boolean::Constant(b), Boolean::Constant(c)) if b == c => {
+                return Ok(Boolean::Constant(b));
+            },
+            (a, Boolean::Constant(true), Boolean::Constant(false)) => return Ok(a),
+            (a, Boolean::Constant(false), Boolean::Constant(true)) => return Ok(a.not()),
+            _ => {}
+        }
+
+        let (a_val, b_val, c_val) = (a.get_value(), b.get_value(), c.get_value());
+        let ch = Boolean::alloc_determined(cs, || {
+            Ok(if *a_val.get()? { *b_val.get()? } else { *c_val.get()? })
+        })?;
+
+        let c = c.lc::<E, CS>();
+
+        cs.enforce(
+            a.lc::<E, CS>().lc(),
+            b.lc::<E, CS>().sub(&c).lc(),
+            LcNum::from_bit(&ch).sub(&c).lc()
+        );
+
+        Ok(Boolean::from_bit(ch))
+    }
+
+    /// Computes SHA-256's majority, `(a & b) ^ (a & c) ^ (b & c)`,
+    /// as `b & c + a * (b + c - 2 * (b & c))`. Costs two
+    /// constraints, and at most one when any input is constant.
+    pub fn sha256_maj<E: Engine, CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        a: &Boolean,
+        b: &Boolean,
+        c: &Boolean
+    ) -> Result<Boolean, Error>
+    {
+        match (*a, *b, *c) {
+            (Boolean::Constant(k), x, y) |
+            (x, Boolean::Constant(k), y) |
+            (x, y, Boolean::Constant(k)) => {
+                return if k { x.or(&y, cs) } else { x.and(&y, cs) };
+            },
+            _ => {}
+        }
+
+        let bc = b.and(c, cs)?;
+
+        let (a_val, b_val, c_val) = (a.get_value(), b.get_value(), c.get_value());
+        let maj = Boolean::alloc_determined(cs, || {
+            let (a, b, c) = (*a_val.get()?, *b_val.get()?, *c_val.get()?);
+
+            Ok((a & b) ^ (a & c) ^ (b & c))
+        })?;
+
+        let bc = bc.lc::<E, CS>();
+        let mut two = E::Fr::one();
+        two.double();
+
+        cs.enforce(
+            a.lc::<E, CS>().lc(),
+            b.lc::<E, CS>().add(&c.lc::<E, CS>()).sub(&bc.scale(two)).lc(),
+            LcNum::from_bit(&maj).sub(&bc).lc()
+        );
+
+        Ok(Boolean::from_bit(maj))
+    }
+
+    /// Returns whether the little-endian number `a` is less than
+    /// the little-endian number `b`, as the final borrow of
+    /// `a - b`. The shorter of the two is extended with zeroes.
+    /// Costs at most four constraints per bit, and two when one
+    /// of the bits is constant.
+    pub fn less_than<E: Engine, CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        a: &[Boolean],
+        b: &[Boolean]
+    ) -> Result<Boolean, Error>
+    {
+        let mut borrow = Boolean::Constant(false);
+
+        for i in 0..::std::cmp::max(a.len(), b.len()) {
+            let a = a.get(i).cloned().unwrap_or(Boolean::Constant(false));
+            let b = b.get(i).cloned().unwrap_or(Boolean::Constant(false));
+
+            // borrow = (!a & b) | (!(a ^ b) & borrow)
+            let t1 = a.xor(&b, cs)?;
+            let t2 = a.not().and(&b, cs)?;
+            let t3 = t1.not().and(&borrow, cs)?;
+
+            borrow = t2.or(&t3, cs)?;
+        }
+
+        Ok(borrow)
+    }
+
+    /// Returns whether the little-endian number `a` is less than
+    /// `constant`.
+    pub fn less_than_constant<E, CS, R>(
+        cs: &mut CS,
+        a: &[Boolean],
+        constant: R
+    ) -> Result<Boolean, Error>
+        where E: Engine, CS: ConstraintSystem<E>, R: AsRef<[u64]>
+    {
+        let mut constant_bits = BitIterator::new(constant).map(Boolean::Constant).collect::<Vec<_>>();
+        constant_bits.reverse();
+
+        Boolean::less_than(cs, a, &constant_bits)
+    }
+}
+
+#[test]
+fn test_comparisons() {
+    use pairing::bls12_381::{Bls12, Fr};
+
+    let rng = &mut thread_rng();
+
+    let fr = |i: u64| Fr::from_repr(i.into()).unwrap();
+    let b = |v: bool| if v { Fr::one() } else { Fr::zero() };
+
+    #[derive(Copy, Clone)]
+    enum Op {
+        LessThan,
+        LessThanConstant(u64),
+        InRange(u64, u64)
+    }
+
+    struct MyComparisonCircuit {
+        a: Assignment<Fr>,
+        b: Assignment<Fr>,
+        op: Op
+    }
+
+    impl Circuit<Bls12> for MyComparisonCircuit {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let a = Num::alloc(cs, self.a)?;
+            let b = Num::alloc(cs, self.b)?;
+
+            let inputs = PublicInputs::new().num(&a).num(&b);
+
+            Ok(match self.op {
+                Op::LessThan => {
+                    let lt = a.less_than(cs, &b, 3)?;
+                    inputs.boolean::<CS>(&lt)
+                },
+                Op::LessThanConstant(c) => {
+                    let lt = a.less_than_constant(cs, Fr::from_repr(c.into()).unwrap(), 3)?;
+                    inputs.boolean::<CS>(&lt)
+                },
+                Op::InRange(lo, hi) => {
+                    a.assert_in_range(cs, Fr::from_repr(lo.into()).unwrap(), Fr::from_repr(hi.into()).unwrap())?;
+                    inputs
+                }
+            })
+        }
+    }
+
+    let setup = |op: Op, rng: &mut ::rand::ThreadRng| {
+        input::generate_input_parameters(MyComparisonCircuit {
+            a: Assignment::unknown(),
+            b: Assignment::unknown(),
+            op: op
+        }, rng).unwrap()
+    };
+
+    let prove = |params: &::bellman::groth16::Parameters<Bls12>, op: Op, a: u64, b: u64, rng: &mut ::rand::ThreadRng| {
+        create_random_proof::<Bls12, _, _, _>(MyComparisonCircuit {
+            a: Assignment::known(fr(a)),
+            b: Assignment::known(fr(b)),
+            op: op
+        }, params, rng).unwrap()
+    };
+
+    // Comparisons of all pairs of 3-bit numbers.
+    let (params, ivk) = setup(Op::LessThan, rng);
+    for x in 0..8 {
+        for y in 0..8 {
+            let proof = prove(&params, Op::LessThan, x, y, rng);
+
+            assert!(input::verify_with_inputs(&ivk, &proof, &[fr(x), fr(y), b(x < y)]).unwrap());
+            assert!(!input::verify_with_inputs(&ivk, &proof, &[fr(x), fr(y), b(x >= y)]).unwrap());
+        }
+    }
+
+    // Operands out of range are rejected.
+    let proof = prove(&params, Op::LessThan, 8, 1, rng);
+    assert!(!input::verify_with_inputs(&ivk, &proof, &[fr(8), fr(1), b(false)]).unwrap());
+    assert!(!input::verify_with_inputs(&ivk, &proof, &[fr(8), fr(1), b(true)]).unwrap());
+
+    // Comparisons of all 3-bit numbers against constants.
+    for c in 0..10 {
+        let op = Op::LessThanConstant(c);
+        let (params, ivk) = setup(op, rng);
+
+        for x in 0..8 {
+            let proof = prove(&params, op, x, 0, rng);
+
+            assert!(input::verify_with_inputs(&ivk, &proof, &[fr(x), fr(0), b(x < c)]).unwrap());
+            assert!(!input::verify_with_inputs(&ivk, &proof, &[fr(x), fr(0), b(x >= c)]).unwrap());
+        }
+    }
+
+    // Range checks of all values around all ranges within 0..4.
+    for lo in 0..4 {
+        for hi in lo..4 {
+            let op = Op::InRange(lo, hi);
+            let (params, ivk) = setup(op, rng);
+
+            for x in 0..6 {
+                let proof = prove(&params, op, x, 0, rng);
+
+                assert_eq!(
+                    input::verify_with_inputs(&ivk, &proof, &[fr(x), fr(0)]).unwrap(),
+                    lo <= x && x <= hi
+                );
+            }
+        }
+    }
+}
+
+/// Computes the inverse of `exponent` modulo `p - 1` as
+/// little-endian limbs, so that raising to it takes
+/// `exponent`-th roots.
+fn exponent_inverse<E: Engine>(exponent: u64) -> Vec<u64> {
+    // p - 1 in 32-bit digits, so that the arithmetic below can't
+    // overflow. The modulus is odd.
+    let mut digits = vec![];
+    for (i, &limb) in E::Fr::char().as_ref().iter().enumerate() {
+        let limb = if i == 0 { limb - 1 } else { limb };
+        digits.push(limb & 0xffffffff);
+        digits.push(limb >> 32);
+    }
+
+    // Find the multiple k(p - 1) + 1 which is divisible by the
+    // exponent; the quotient is the inverse.
+    let rem = digits.iter().rev().fold(0, |r, &d| ((r << 32) + d) % exponent);
+    let k = (1..exponent).find(|k| (rem * k + 1) % exponent == 0)
+                         .expect("exponent is not invertible");
+
+    let mut carry = 1;
+    for d in digits.iter_mut() {
+        let t = *d * k + carry;
+        *d = t & 0xffffffff;
+        carry = t >> 32;
+    }
+    digits.push(carry);
+
+    let mut r = 0;
+    for d in digits.iter_mut().rev() {
+        let t = (r << 32) + *d;
+        *d = t / exponent;
+        r = t % exponent;
+    }
+    assert_eq!(r, 0);
+
+    // The quotient is less than p - 1, so it fits in the
+    // original digits.
+    assert_eq!(digits.pop(), Some(0));
+
+    digits.chunks(2).map(|c| c[0] | (c.get(1).map_or(0, |&hi| hi << 32))).collect()
 }
 
-/// Takes little-endian order bits, subtracts Fr and asserts
-/// no carry.
+/// Takes little-endian order bits and asserts that they are less
+/// than the modulus.
 fn assert_less_than_r<E: Engine, CS: ConstraintSystem<E>>(bits: &[Bit], cs: &mut CS)
     -> Result<(), Error>
 {
-    let mut r_bits = vec![];
-    for b in BitIterator::new(Fr::char()) {
-        r_bits.push(FunBit::Constant(b));
+    let bits = bits.iter().cloned().map(Boolean::from_bit).collect::<Vec<_>>();
+
+    Boolean::less_than_constant(cs, &bits, E::Fr::char())?.assert_is_true(cs);
+
+    Ok(())
+}
+
+#[test]
+fn test_less_than_r() {
+    use pairing::bls12_381::{Bls12, Fr};
+
+    let rng = &mut thread_rng();
+
+    struct MyCircuit {
+        bits: Vec<Assignment<bool>>
     }
-    r_bits.reverse();
-    r_bits.truncate(E::Fr::num_bits() as usize);
 
-    let mut carry = FunBit::Constant(false);
+    impl Circuit<Bls12> for MyCircuit {
+        type InputMap = PublicInputs<Bls12>;
 
-    for (a, b) in r_bits.into_iter().zip(bits.iter().cloned()) {
-        let b = FunBit::from_bit(b);
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut bits = vec![];
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
 
-        let t1 = a.xor(&b, cs)?;
-        let t2 = a.not().and(&b, cs)?;
-        let t3 = t1.not().and(&carry, cs)?;
-        let t4 = t2.or(&t3, cs)?;
+            assert_less_than_r(&bits, cs)?;
 
-        carry = t4;
+            Ok(PublicInputs::new())
+        }
     }
 
-    // dirty and somewhat cheap
-    carry.assert_is_false(cs);
+    let (params, ivk) = input::generate_input_parameters(MyCircuit {
+        bits: vec![Assignment::unknown(); Fr::num_bits() as usize]
+    }, rng).unwrap();
 
-    Ok(())
+    let r = Fr::char();
+    let mut r_minus_one = r;
+    r_minus_one.sub_noborrow(&1.into());
+
+    for &(repr, valid) in &[(r_minus_one, true), (r, false)] {
+        let mut bits = BitIterator::new(repr).collect::<Vec<_>>();
+        bits.reverse();
+        bits.truncate(Fr::num_bits() as usize);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyCircuit {
+            bits: bits.into_iter().map(Assignment::known).collect()
+        }, &params, rng).unwrap();
+
+        assert_eq!(input::verify_with_inputs(&ivk, &proof, &[]).unwrap(), valid);
+    }
 }
 
 #[test]
@@ -1091,17 +2781,8 @@ fn testy_more_pedersen()
         }
     }
 
-    struct MyLookupCircuitInput;
-
-    impl<E: Engine> Input<E> for MyLookupCircuitInput {
-        fn synthesize<CS: PublicConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), Error>
-        {
-            Ok(())
-        }
-    }
-
     impl<'a> Circuit<Bls12> for MyLookupCircuit<'a> {
-        type InputMap = MyLookupCircuitInput;
+        type InputMap = PublicInputs<Bls12>;
 
         fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
         {
@@ -1112,11 +2793,13 @@ fn testy_more_pedersen()
 
             const DEPTH: usize = 50;
 
+            let mut num = None;
+
             for i in 0..DEPTH {
-                let num = pedersen_hash(cs, &bits, self.generators, self.j)?;
+                let cur = pedersen_hash(cs, &bits, self.generators, self.j)?;
 
                 if i != (DEPTH - 1) {
-                    bits = num.unpack(cs)?;
+                    bits = cur.unpack(cs)?;
                     assert_eq!(bits.len(), 255);
                     for b in self.bits.iter().take(255) {
                         bits.push(Bit::alloc(cs, *b)?);
@@ -1126,16 +2809,17 @@ fn testy_more_pedersen()
 
                     assert_eq!(bits.len(), 512);
                 }
+
+                num = Some(cur);
             }
 
-            Ok(MyLookupCircuitInput)
+            Ok(PublicInputs::new().num(&num.unwrap()))
         }
     }
 
     let j = JubJub::new();
     let generators = generate_constant_table(rng, &j);
-    let params = generate_random_parameters::<Bls12, _, _>(MyLookupCircuit::blank(&generators, &j), rng).unwrap();
-    let prepared_vk = prepare_verifying_key(&params.vk);
+    let (params, ivk) = input::generate_input_parameters(MyLookupCircuit::blank(&generators, &j), rng).unwrap();
 
     let mut bits;
     let mut proof;
@@ -1167,34 +2851,23 @@ fn testy_more_pedersen()
     println!("each proof took on average {:?}", elapsed / 15);
 
     let expected_result = {
-        let mut cur = Point::zero();
-
-        for (chunk, g) in bits.chunks(4).zip(generators.iter()) {
-            let mut idx = 0;
-            for c in chunk.iter().rev() {
-                idx <<= 1;
-
-                if *c {
-                    idx |= 1;
-                }
-            }
+        let mut cur = pedersen_hash_native(&bits, &generators, &j);
 
-            let new_point = Point {
-                x: g.0[idx],
-                y: g.1[idx]
-            };
+        for _ in 1..50 {
+            let mut next = BitIterator::new(cur.into_repr()).collect::<Vec<_>>();
+            next.reverse();
+            next.truncate(255);
+            next.extend(bits.iter().take(255).cloned());
+            next.push(true);
+            next.push(true);
 
-            cur.add_assign(&new_point, &j);
+            cur = pedersen_hash_native(&next, &generators, &j);
         }
 
-        cur.y
+        cur
     };
 
-    assert!(verify_proof(&prepared_vk, &proof, |cs| {
-        let r_var = cs.alloc(|| Ok(expected_result))?;
-
-        Ok(MyLookupCircuitInput)
-    }).unwrap());
+    assert!(input::verify_with_inputs(&ivk, &proof, &[expected_result]).unwrap());
 }
 
 
@@ -1213,133 +2886,3 @@ use bellman::groth16::{
     create_random_proof,
     verify_proof,
 };
-
-
-pub const MIMC_ROUNDS: usize = 300;
-
-
-pub fn mimc<E: Engine>(
-    mut xl: E::Fr,
-    mut xr: E::Fr,
-    constants: &[E::Fr]
-) -> E::Fr
-{
-    assert_eq!(constants.len(), MIMC_ROUNDS);
-
-    for i in 0..MIMC_ROUNDS {
-        let mut tmp1 = xl;
-        tmp1.add_assign(&constants[i]);
-        let mut tmp2 = tmp1;
-        tmp2.square();
-        tmp2.mul_assign(&tmp1);
-        tmp2.add_assign(&xr);
-        xr = xl;
-        xl = tmp2;
-    }
-
-    xl
-}
-
-/// This is our demo circuit for proving knowledge of the
-/// preimage of a MiMC hash invocation.
-pub struct MiMCDemo<'a, E: Engine> {
-    pub xl: Option<E::Fr>,
-    pub xr: Option<E::Fr>,
-    pub constants: &'a [E::Fr]
-}
-
-pub struct MiMCDemoCircuitInput;
-
-impl<E: Engine> Input<E> for MiMCDemoCircuitInput {
-    fn synthesize<CS: PublicConstraintSystem<E>>(self, _: &mut CS) -> Result<(), Error>
-    {
-        Ok(())
-    }
-}
-
-/// Our demo circuit implements this `Circuit` trait which
-/// is used during paramgen and proving in order to
-/// synthesize the constraint system.
-impl<'a, E: Engine> Circuit<E> for MiMCDemo<'a, E> {
-    type InputMap = MiMCDemoCircuitInput;
-
-    fn synthesize<CS: ConstraintSystem<E>>(
-        self,
-        cs: &mut CS
-    ) -> Result<Self::InputMap, Error>
-    {
-        assert_eq!(self.constants.len(), MIMC_ROUNDS);
-
-        // Allocate the first component of the preimage.
-        let mut xl_value = self.xl;
-        let mut xl = cs.alloc(||  {
-            xl_value.ok_or(Error::AssignmentMissing)
-        })?;
-
-        // Allocate the second component of the preimage.
-        let mut xr_value = self.xr;
-        let mut xr = cs.alloc(||  {
-            xr_value.ok_or(Error::AssignmentMissing)
-        })?;
-
-        for i in 0..MIMC_ROUNDS {
-            // xL, xR := xR + (xL + Ci)^3, xL
-//            let cs = &mut cs.namespace(|| format!("round {}", i));
-
-            // tmp = (xL + Ci)^2
-            let mut tmp_value = xl_value.map(|mut e| {
-                e.add_assign(&self.constants[i]);
-                e.square();
-                e
-            });
-            let mut tmp = cs.alloc(||  {
-                tmp_value.ok_or(Error::AssignmentMissing)
-            })?;
-
-            cs.enforce(
-                LinearCombination::zero() + xl + (self.constants[i], CS::one()),
-                LinearCombination::zero() + xl + (self.constants[i], CS::one()),
-                LinearCombination::zero() + tmp
-            );
-
-            // new_xL = xR + (xL + Ci)^3
-            // new_xL = xR + tmp * (xL + Ci)
-            // new_xL - xR = tmp * (xL + Ci)
-            let mut new_xl_value = xl_value.map(|mut e| {
-                e.add_assign(&self.constants[i]);
-                e.mul_assign(&tmp_value.unwrap());
-                e.add_assign(&xr_value.unwrap());
-                e
-            });
-
-            let mut new_xl = if i == (MIMC_ROUNDS-1) {
-                // This is the last round, xL is our image and so
-                // we allocate a public input.
-                cs.alloc(||  {
-                    new_xl_value.ok_or(Error::AssignmentMissing)
-                })?
-            } else {
-                cs.alloc(||  {
-                    new_xl_value.ok_or(Error::AssignmentMissing)
-                })?
-            };
-
-            cs.enforce(
-                LinearCombination::zero() + tmp,
-                LinearCombination::zero() + xl + (self.constants[i], CS::one()),
-                LinearCombination::zero() + new_xl - xr
-            );
-
-            // xR = xL
-            xr = xl;
-            xr_value = xl_value;
-
-            // xL = new_xL
-            xl = new_xl;
-            xl_value = new_xl_value;
-        }
-
-        Ok(MiMCDemoCircuitInput)
-    }
-}
-
@@ -0,0 +1,189 @@
+//! Merkle tree authentication paths over the Pedersen hash,
+//! natively and as a gadget.
+//!
+//! A node is the Pedersen hash of the strict little-endian bits
+//! of its left child, then of its right child, then two set bits
+//! to fill the 512 bits the hash takes. Each step of a path is a
+//! sibling and whether the node being authenticated is the right
+//! child, so a path runs from the leaf up to the root.
+
+use pairing::*;
+use pairing::bls12_381::{Bls12, Fr};
+use bellman::*;
+
+use super::{Bit, JubJub, LcNum, Num, assert_less_than_r, pedersen_hash, pedersen_hash_native};
+
+/// The strict little-endian bits of `num`.
+fn num_bits(num: &Fr) -> Vec<bool> {
+    let mut bits = BitIterator::new(num.into_repr()).collect::<Vec<_>>();
+    bits.reverse();
+    bits.truncate(Fr::num_bits() as usize);
+
+    bits
+}
+
+/// Hashes `left` and `right` into their parent.
+pub fn merkle_hash_native(
+    left: &Fr,
+    right: &Fr,
+    generators: &[(Vec<Fr>, Vec<Fr>)],
+    j: &JubJub
+) -> Fr
+{
+    let mut bits = num_bits(left);
+    bits.extend(num_bits(right));
+    bits.push(true);
+    bits.push(true);
+
+    pedersen_hash_native(&bits, generators, j)
+}
+
+/// The native equivalent of `merkle_path_gadget`, for computing
+/// witnesses and expected roots.
+pub fn merkle_path_native(
+    leaf: &Fr,
+    path: &[(Fr, bool)],
+    generators: &[(Vec<Fr>, Vec<Fr>)],
+    j: &JubJub
+) -> Fr
+{
+    path.iter().fold(*leaf, |cur, &(ref sibling, is_right)| {
+        if is_right {
+            merkle_hash_native(sibling, &cur, generators, j)
+        } else {
+            merkle_hash_native(&cur, sibling, generators, j)
+        }
+    })
+}
+
+/// The same as `merkle_hash_native`, in a circuit. Unpacking both
+/// children strictly and hashing them costs 2,817 constraints.
+pub fn merkle_hash_gadget<CS>(
+    cs: &mut CS,
+    left: &LcNum<Bls12>,
+    right: &LcNum<Bls12>,
+    generators: &[(Vec<Fr>, Vec<Fr>)],
+    j: &JubJub
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    let mut bits = vec![];
+    for child in &[left, right] {
+        let child_bits = child.unpack_into(cs, Fr::num_bits() as usize)?;
+        assert_less_than_r(&child_bits, cs)?;
+
+        bits.extend(child_bits);
+    }
+
+    bits.push(Bit::one(cs));
+    bits.push(Bit::one(cs));
+
+    pedersen_hash(cs, &bits, generators, j)
+}
+
+/// Computes the root of the tree of depth `depth` in which `leaf`
+/// has the authentication path `path`.
+///
+/// Each level swaps the node and its sibling by the position bit
+/// with `Num::swap_if`, whose one constraint brings a level to
+/// 2,818 constraints.
+pub fn merkle_path_gadget<CS>(
+    cs: &mut CS,
+    leaf: &Num<Bls12>,
+    path: &[(Num<Bls12>, Bit)],
+    depth: usize,
+    generators: &[(Vec<Fr>, Vec<Fr>)],
+    j: &JubJub
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    assert_eq!(path.len(), depth);
+
+    let mut cur = leaf.clone();
+
+    for &(ref sibling, ref is_right) in path {
+        let (left, right) = Num::swap_if(cs, is_right, &cur, sibling)?;
+
+        cur = merkle_hash_gadget(cs, &LcNum::from(left), &right, generators, j)?;
+    }
+
+    Ok(cur)
+}
+
+#[test]
+fn test_merkle_path() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use super::{Assignment, ConstraintCounter, generate_constant_table};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+    let j = JubJub::new();
+    let generators = generate_constant_table(rng, &j);
+
+    const DEPTH: usize = 4;
+
+    struct MyMerkleCircuit<'a> {
+        leaf: Assignment<Fr>,
+        path: Vec<(Assignment<Fr>, Assignment<bool>)>,
+        generators: &'a [(Vec<Fr>, Vec<Fr>)],
+        j: &'a JubJub,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyMerkleCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let leaf = Num::alloc(cs, self.leaf)?;
+
+            let mut path = vec![];
+            for (sibling, is_right) in self.path {
+                path.push((Num::alloc(cs, sibling)?, Bit::alloc(cs, is_right)?));
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let root = merkle_path_gadget(&mut counter, &leaf, &path, DEPTH, self.generators, self.j)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(&root))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let (params, ivk) = input::generate_input_parameters(MyMerkleCircuit {
+        leaf: Assignment::unknown(),
+        path: vec![(Assignment::unknown(), Assignment::unknown()); DEPTH],
+        generators: &generators,
+        j: &j,
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+
+    assert_eq!(num_constraints.get(), DEPTH * 2818);
+
+    for _ in 0..3 {
+        let leaf: Fr = rng.gen();
+        let path = (0..DEPTH).map(|_| (rng.gen(), rng.gen())).collect::<Vec<(Fr, bool)>>();
+
+        let root = merkle_path_native(&leaf, &path, &generators, &j);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyMerkleCircuit {
+            leaf: Assignment::known(leaf),
+            path: path.iter().map(|&(s, b)| (Assignment::known(s), Assignment::known(b))).collect(),
+            generators: &generators,
+            j: &j,
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[root]).unwrap());
+
+        // Flipping a position bit changes the root.
+        let mut flipped = path.clone();
+        flipped[0].1 = !flipped[0].1;
+        let wrong_root = merkle_path_native(&leaf, &flipped, &generators, &j);
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[wrong_root]).unwrap());
+    }
+}
@@ -0,0 +1,892 @@
+//! The MiMC Feistel permutation, natively and as a gadget.
+//!
+//! Each round maps `(xL, xR)` to `(xR + (xL + Ci)^e, xL)` for a
+//! small exponent `e`. The hash of a pair is the left half after
+//! every round. The number of rounds is the number of round
+//! constants.
+//!
+//! The sponge hashes any number of field elements. Its state is
+//! the two halves of the permutation: the left half is the rate,
+//! to which each input is added before permuting, and the right
+//! half is the capacity. A one is absorbed after the inputs, so
+//! that inputs of different lengths can't collide.
+//!
+//! The block cipher maps `x` to `(x + k + Ci)^e` each round, and
+//! adds the key once more at the end. Decryption takes `e`-th
+//! roots, by raising to the inverse of `e` modulo `p - 1`.
+//! Vectors are encrypted in counter mode, adding the encryption
+//! of the counter `mimc(nonce, i)` to the `i`th element, so a
+//! circuit only ever evaluates the cipher forwards. Hashing the
+//! counter keeps the key streams of different nonces apart; with
+//! `nonce + i`, the nonces `n` and `n + 1` would share all but
+//! one element.
+//! `mimc_committed_ctr_encrypt_gadget` binds the ciphertexts to
+//! a key the verifier only knows by its sponge commitment.
+
+use pairing::*;
+use bellman::*;
+use rand::Rng;
+
+use super::{Assignment, LcNum, Num, exponent_inverse};
+use blake2s::hash_to_field;
+use input::PublicInputs;
+
+/// The domain `MimcParams::default()` derives its constants from.
+pub const DEFAULT_DOMAIN: &'static [u8] = b"jubjub_mimc_default";
+
+/// Errors arising when choosing MiMC parameters.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MimcError {
+    /// Only the exponents 3, 5 and 7 are supported.
+    UnsupportedExponent(u64),
+    /// `x^exponent` isn't a permutation of the field, because
+    /// the exponent divides `p - 1`.
+    NotAPermutation(u64)
+}
+
+/// The exponent, and a round constant for each round.
+#[derive(Clone)]
+pub struct MimcParams<E: Engine> {
+    exponent: u64,
+    constants: Vec<E::Fr>
+}
+
+impl<E: Engine> MimcParams<E> {
+    /// Uses the given round constants, checking that
+    /// `x^exponent` is a permutation of the field. The number of
+    /// rounds isn't checked against `min_rounds`.
+    pub fn new(exponent: u64, constants: Vec<E::Fr>) -> Result<MimcParams<E>, MimcError> {
+        check_exponent::<E>(exponent)?;
+
+        Ok(MimcParams {
+            exponent: exponent,
+            constants: constants
+        })
+    }
+
+    /// Samples `min_rounds(exponent)` random round constants.
+    pub fn rand<R: Rng>(rng: &mut R, exponent: u64) -> Result<MimcParams<E>, MimcError> {
+        check_exponent::<E>(exponent)?;
+
+        let constants = (0..MimcParams::<E>::min_rounds(exponent)).map(|_| rng.gen()).collect();
+
+        MimcParams::new(exponent, constants)
+    }
+
+    /// Derives `min_rounds` round constants from `domain`, using
+    /// the smallest supported exponent that is a permutation of
+    /// the field. Constant `i` is `hash_to_field(domain, i)`, so
+    /// the same domain always gives the same parameters.
+    pub fn from_seed(domain: &[u8]) -> MimcParams<E> {
+        let exponent = [3, 5, 7].iter().cloned()
+                                .find(|&e| check_exponent::<E>(e).is_ok())
+                                .expect("no supported exponent is a permutation of the field");
+
+        let constants = (0..MimcParams::<E>::min_rounds(exponent)).map(|i| {
+            hash_to_field::<E>(domain, i as u32)
+        }).collect();
+
+        MimcParams::new(exponent, constants).unwrap()
+    }
+
+    /// The number of rounds needed against interpolation attacks,
+    /// `2 * ceil(log_exponent(p))`: each round raises the degree
+    /// of the permutation by a factor of the exponent, and it
+    /// takes two rounds to mix both halves.
+    pub fn min_rounds(exponent: u64) -> usize {
+        let field_bits = E::Fr::num_bits() as f64;
+
+        2 * (field_bits / (exponent as f64).log2()).ceil() as usize
+    }
+
+    pub fn exponent(&self) -> u64 {
+        self.exponent
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn constants(&self) -> &[E::Fr] {
+        &self.constants
+    }
+}
+
+impl<E: Engine> Default for MimcParams<E> {
+    /// The parameters derived from `DEFAULT_DOMAIN`.
+    fn default() -> MimcParams<E> {
+        MimcParams::from_seed(DEFAULT_DOMAIN)
+    }
+}
+
+/// Checks that the exponent is supported and that `x^exponent`
+/// is a permutation, which is the case when it shares no factor
+/// with `p - 1`. The supported exponents are prime, so it
+/// suffices to check that `p - 1` isn't a multiple.
+fn check_exponent<E: Engine>(exponent: u64) -> Result<(), MimcError> {
+    match exponent {
+        3 | 5 | 7 => {},
+        _ => return Err(MimcError::UnsupportedExponent(exponent))
+    }
+
+    let mut modulus = 0;
+    for b in BitIterator::new(E::Fr::char()) {
+        modulus = (modulus * 2 + b as u64) % exponent;
+    }
+
+    if modulus == 1 {
+        Err(MimcError::NotAPermutation(exponent))
+    } else {
+        Ok(())
+    }
+}
+
+/// Computes `xR + (xL + c)^exponent`.
+fn round_native<E: Engine>(xl: E::Fr, xr: E::Fr, c: &E::Fr, exponent: u64) -> E::Fr {
+    let mut tmp = xl;
+    tmp.add_assign(c);
+    let mut tmp = tmp.pow(&[exponent]);
+    tmp.add_assign(&xr);
+
+    tmp
+}
+
+/// Applies the Feistel permutation to `(xl, xr)`, returning both
+/// halves.
+pub fn mimc_permutation<E: Engine>(
+    mut xl: E::Fr,
+    mut xr: E::Fr,
+    params: &MimcParams<E>
+) -> (E::Fr, E::Fr)
+{
+    for c in &params.constants {
+        let new_xl = round_native::<E>(xl, xr, c, params.exponent);
+        xr = xl;
+        xl = new_xl;
+    }
+
+    (xl, xr)
+}
+
+/// Inverts `mimc_permutation`, running the rounds backwards.
+pub fn mimc_permutation_inverse<E: Engine>(
+    mut xl: E::Fr,
+    mut xr: E::Fr,
+    params: &MimcParams<E>
+) -> (E::Fr, E::Fr)
+{
+    for c in params.constants.iter().rev() {
+        // xL was xR + (xL + Ci)^e, and xR was xL.
+        let mut tmp = xr;
+        tmp.add_assign(c);
+        xl.sub_assign(&tmp.pow(&[params.exponent]));
+        ::std::mem::swap(&mut xl, &mut xr);
+    }
+
+    (xl, xr)
+}
+
+/// The MiMC hash of `(xl, xr)`.
+pub fn mimc<E: Engine>(
+    xl: E::Fr,
+    xr: E::Fr,
+    params: &MimcParams<E>
+) -> E::Fr
+{
+    mimc_permutation(xl, xr, params).0
+}
+
+/// Computes `xR + (xL + c)^exponent`. Costs two constraints for
+/// an exponent of 3, and three and four for 5 and 7.
+fn round<E, CS>(
+    cs: &mut CS,
+    xl: &LcNum<E>,
+    xr: &LcNum<E>,
+    c: E::Fr,
+    exponent: u64
+) -> Result<Num<E>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    // tmp = (xL + Ci)^(e - 1)
+    let t = xl.add(&LcNum::constant::<CS>(c));
+    let tmp = t.pow(cs, exponent - 1)?;
+
+    // new_xL - xR = tmp * (xL + Ci)
+    let mut new_xl_value = Assignment::unknown();
+    let new_xl = cs.alloc(|| {
+        let mut e = *tmp.get_value().get()?;
+        e.mul_assign(t.get_value().get()?);
+        e.add_assign(xr.get_value().get()?);
+
+        new_xl_value = Assignment::known(e);
+
+        Ok(e)
+    })?;
+
+    cs.enforce(
+        tmp.lc(),
+        t.lc(),
+        LinearCombination::zero() + new_xl - &xr.lc()
+    );
+
+    Ok(Num {
+        value: new_xl_value,
+        var: new_xl
+    })
+}
+
+/// The same as `mimc_permutation`, in a circuit. The halves
+/// of the input can be linear combinations, since each round
+/// only uses them linearly. `params` must have at least two
+/// rounds, so that both halves of the result are variables.
+pub fn mimc_permutation_gadget<E, CS>(
+    cs: &mut CS,
+    xl: &LcNum<E>,
+    xr: &LcNum<E>,
+    params: &MimcParams<E>
+) -> Result<(Num<E>, Num<E>), Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert!(params.constants.len() >= 2);
+
+    // The first two rounds replace both halves with variables.
+    let mut xr = round(cs, xl, xr, params.constants[0], params.exponent)?;
+    let mut xl = round(cs, &xr.clone().into(), xl, params.constants[1], params.exponent)?;
+
+    for c in &params.constants[2..] {
+        let new_xl = round(cs, &xl.clone().into(), &xr.into(), *c, params.exponent)?;
+
+        xr = xl;
+        xl = new_xl;
+    }
+
+    Ok((xl, xr))
+}
+
+/// The same as `mimc`, in a circuit.
+pub fn mimc_gadget<E, CS>(
+    cs: &mut CS,
+    xl: &Num<E>,
+    xr: &Num<E>,
+    params: &MimcParams<E>
+) -> Result<Num<E>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    Ok(mimc_permutation_gadget(cs, &xl.clone().into(), &xr.clone().into(), params)?.0)
+}
+
+/// Hashes any number of field elements with the sponge.
+pub fn mimc_sponge<E: Engine>(
+    inputs: &[E::Fr],
+    params: &MimcParams<E>
+) -> E::Fr
+{
+    let mut xl = E::Fr::zero();
+    let mut xr = E::Fr::zero();
+
+    for x in inputs.iter().chain(Some(&E::Fr::one())) {
+        xl.add_assign(x);
+
+        let (l, r) = mimc_permutation(xl, xr, params);
+        xl = l;
+        xr = r;
+    }
+
+    xl
+}
+
+/// The same as `mimc_sponge`, in a circuit. The state starts
+/// as constants and inputs are absorbed into it linearly, so
+/// this costs only a permutation per input, plus another for the
+/// padding.
+pub fn mimc_sponge_gadget<E, CS, N>(
+    cs: &mut CS,
+    inputs: &[N],
+    params: &MimcParams<E>
+) -> Result<Num<E>, Error>
+    where E: Engine, CS: ConstraintSystem<E>, N: Clone + Into<LcNum<E>>
+{
+    let mut xl = LcNum::zero();
+    let mut xr = LcNum::zero();
+
+    for x in inputs {
+        let (l, r) = mimc_permutation_gadget(cs, &xl.add(&x.clone().into()), &xr, params)?;
+        xl = l.into();
+        xr = r.into();
+    }
+
+    let padding = LcNum::constant::<CS>(E::Fr::one());
+
+    Ok(mimc_permutation_gadget(cs, &xl.add(&padding), &xr, params)?.0)
+}
+
+/// Encrypts a field element with the block cipher, using a round
+/// for each of the parameters' constants.
+pub fn mimc_encrypt<E: Engine>(
+    key: E::Fr,
+    plaintext: E::Fr,
+    params: &MimcParams<E>
+) -> E::Fr
+{
+    let mut x = plaintext;
+
+    for c in &params.constants {
+        x.add_assign(&key);
+        x.add_assign(c);
+        x = x.pow(&[params.exponent]);
+    }
+
+    x.add_assign(&key);
+
+    x
+}
+
+/// Inverts `mimc_encrypt`.
+pub fn mimc_decrypt<E: Engine>(
+    key: E::Fr,
+    ciphertext: E::Fr,
+    params: &MimcParams<E>
+) -> E::Fr
+{
+    let root = exponent_inverse::<E>(params.exponent);
+
+    let mut x = ciphertext;
+    x.sub_assign(&key);
+
+    for c in params.constants.iter().rev() {
+        x = x.pow(&root);
+        x.sub_assign(c);
+        x.sub_assign(&key);
+    }
+
+    x
+}
+
+/// The element of the key stream at `i`, the encryption of
+/// `mimc(nonce, i)`.
+fn key_stream<E: Engine>(
+    key: E::Fr,
+    nonce: E::Fr,
+    i: usize,
+    params: &MimcParams<E>
+) -> E::Fr
+{
+    let i = E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(i as u64)).unwrap();
+
+    mimc_encrypt(key, mimc(nonce, i, params), params)
+}
+
+/// Encrypts a vector of field elements in counter mode. A nonce
+/// must never be reused with the same key, but nonces needn't be
+/// random: a counter works.
+pub fn mimc_ctr_encrypt<E: Engine>(
+    key: E::Fr,
+    nonce: E::Fr,
+    plaintext: &[E::Fr],
+    params: &MimcParams<E>
+) -> Vec<E::Fr>
+{
+    plaintext.iter().enumerate().map(|(i, m)| {
+        let mut c = key_stream(key, nonce, i, params);
+        c.add_assign(m);
+        c
+    }).collect()
+}
+
+/// Inverts `mimc_ctr_encrypt`.
+pub fn mimc_ctr_decrypt<E: Engine>(
+    key: E::Fr,
+    nonce: E::Fr,
+    ciphertext: &[E::Fr],
+    params: &MimcParams<E>
+) -> Vec<E::Fr>
+{
+    ciphertext.iter().enumerate().map(|(i, c)| {
+        let mut m = *c;
+        m.sub_assign(&key_stream(key, nonce, i, params));
+        m
+    }).collect()
+}
+
+/// Encrypts the linear combination `x`, returning the ciphertext
+/// as a linear combination.
+fn encrypt_lc<E, CS>(
+    cs: &mut CS,
+    key: &LcNum<E>,
+    x: LcNum<E>,
+    params: &MimcParams<E>
+) -> Result<LcNum<E>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut x = x;
+
+    for c in &params.constants {
+        let t = x.add(key).add(&LcNum::constant::<CS>(*c));
+        x = t.pow(cs, params.exponent)?;
+    }
+
+    Ok(x.add(key))
+}
+
+/// The same as `mimc_encrypt`, in a circuit. Costs an
+/// exponentiation per round, and one constraint for the result.
+pub fn mimc_encrypt_gadget<E, CS>(
+    cs: &mut CS,
+    key: &Num<E>,
+    plaintext: &Num<E>,
+    params: &MimcParams<E>
+) -> Result<Num<E>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let key = LcNum::from(key.clone());
+
+    encrypt_lc(cs, &key, LcNum::from(plaintext.clone()), params)?.into_num(cs)
+}
+
+/// The same as `mimc_ctr_encrypt`, in a circuit. Costs a
+/// permutation and an encryption per element.
+pub fn mimc_ctr_encrypt_gadget<E, CS>(
+    cs: &mut CS,
+    key: &Num<E>,
+    nonce: &Num<E>,
+    plaintext: &[Num<E>],
+    params: &MimcParams<E>
+) -> Result<Vec<Num<E>>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let key = LcNum::from(key.clone());
+
+    let mut result = vec![];
+    let mut i = E::Fr::zero();
+
+    for m in plaintext {
+        let (counter, _) = mimc_permutation_gadget(cs, &nonce.clone().into(), &LcNum::constant::<CS>(i), params)?;
+        let c = encrypt_lc(cs, &key, counter.into(), params)?.add(&LcNum::from(m.clone()));
+
+        result.push(c.into_num(cs)?);
+        i.add_assign(&E::Fr::one());
+    }
+
+    Ok(result)
+}
+
+/// The same as `mimc_ctr_encrypt_gadget`, also enforcing that
+/// `commitment` is `mimc_sponge(&[key])`, so that the ciphertexts
+/// are bound to a key the verifier knows only by its commitment.
+/// Costs two more permutations and a constraint than
+/// `mimc_ctr_encrypt_gadget`.
+pub fn mimc_committed_ctr_encrypt_gadget<E, CS>(
+    cs: &mut CS,
+    commitment: &Num<E>,
+    key: &Num<E>,
+    nonce: &Num<E>,
+    plaintext: &[Num<E>],
+    params: &MimcParams<E>
+) -> Result<Vec<Num<E>>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    mimc_sponge_gadget(cs, &[key.clone()], params)?.enforce_equal(cs, commitment);
+
+    mimc_ctr_encrypt_gadget(cs, key, nonce, plaintext, params)
+}
+
+/// This is our demo circuit for proving knowledge of the
+/// preimage of a MiMC hash invocation.
+pub struct MiMCDemo<'a, E: Engine> {
+    pub xl: Option<E::Fr>,
+    pub xr: Option<E::Fr>,
+    pub params: &'a MimcParams<E>
+}
+
+/// Our demo circuit implements this `Circuit` trait which
+/// is used during paramgen and proving in order to
+/// synthesize the constraint system.
+impl<'a, E: Engine> Circuit<E> for MiMCDemo<'a, E> {
+    type InputMap = PublicInputs<E>;
+
+    fn synthesize<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS
+    ) -> Result<Self::InputMap, Error>
+    {
+        // Allocate the two components of the preimage.
+        let xl = Num::alloc(cs, self.xl.map_or(Assignment::unknown(), Assignment::known))?;
+        let xr = Num::alloc(cs, self.xr.map_or(Assignment::unknown(), Assignment::known))?;
+
+        let image = mimc_gadget(cs, &xl, &xr, self.params)?;
+
+        // The image is exposed as a public input.
+        Ok(PublicInputs::new().num(&image))
+    }
+}
+
+#[test]
+fn test_mimc_params() {
+    use pairing::bls12_381::Bls12;
+    use rand::thread_rng;
+
+    assert_eq!(MimcParams::<Bls12>::min_rounds(3), 322);
+    assert_eq!(MimcParams::<Bls12>::min_rounds(5), 220);
+    assert_eq!(MimcParams::<Bls12>::min_rounds(7), 182);
+
+    // 3 divides p - 1 for the BLS12-381 scalar field.
+    assert_eq!(MimcParams::<Bls12>::new(3, vec![]).err(), Some(MimcError::NotAPermutation(3)));
+    assert_eq!(MimcParams::<Bls12>::new(4, vec![]).err(), Some(MimcError::UnsupportedExponent(4)));
+    assert!(MimcParams::<Bls12>::new(5, vec![]).is_ok());
+    assert!(MimcParams::<Bls12>::new(7, vec![]).is_ok());
+
+    let params = MimcParams::<Bls12>::rand(&mut thread_rng(), 7).unwrap();
+    assert_eq!(params.exponent(), 7);
+    assert_eq!(params.rounds(), 182);
+}
+
+#[test]
+fn test_mimc_default_params() {
+    use pairing::bls12_381::{Bls12, Fr};
+
+    let params = MimcParams::<Bls12>::default();
+    assert_eq!(params.exponent(), 5);
+    assert_eq!(params.rounds(), 220);
+
+    let constants = params.constants();
+    assert_eq!(constants[0], Fr::from_str("164515157303271906026289122238115869344072936908831855586994807713930995214").unwrap());
+    assert_eq!(constants[219], Fr::from_str("11615511879350654352637943036893165767513399446299916091590738434524572771083").unwrap());
+
+    assert_eq!(
+        mimc(Fr::zero(), Fr::zero(), &params),
+        Fr::from_str("48189403429386396201404498680258268748592667684013311572947771275178675170156").unwrap()
+    );
+    assert_eq!(
+        mimc(Fr::one(), Fr::from_str("2").unwrap(), &params),
+        Fr::from_str("35238373701648620161179382618011531641531984853477799115890880080106406565181").unwrap()
+    );
+
+    // Other domains give other constants.
+    let other = MimcParams::<Bls12>::from_seed(b"some other domain");
+    assert!(other.constants()[0] != constants[0]);
+}
+
+#[test]
+fn test_mimc() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{Rng, thread_rng};
+    use input;
+
+    let rng = &mut thread_rng();
+
+    let params = MimcParams::rand(rng, 5).unwrap();
+
+    let (groth_params, ivk) = input::generate_input_parameters(MiMCDemo {
+        xl: None,
+        xr: None,
+        params: &params
+    }, rng).unwrap();
+
+    let xl: Fr = rng.gen();
+    let xr: Fr = rng.gen();
+    let image = mimc::<Bls12>(xl, xr, &params);
+
+    let proof = create_random_proof::<Bls12, _, _, _>(MiMCDemo {
+        xl: Some(xl),
+        xr: Some(xr),
+        params: &params
+    }, &groth_params, rng).unwrap();
+
+    assert!(input::verify_with_inputs(&ivk, &proof, &[image]).unwrap());
+    assert!(!input::verify_with_inputs(&ivk, &proof, &[xl]).unwrap());
+
+    match input::verify_with_inputs(&ivk, &proof, &[image, xl]) {
+        Err(input::InputError::WrongNumberOfInputs { expected: 1, actual: 2 }) => {},
+        _ => panic!("expected an input count mismatch")
+    }
+}
+
+#[test]
+fn test_mimc_permutation() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{Rng, thread_rng};
+    use input;
+
+    let rng = &mut thread_rng();
+
+    struct MyPermutationCircuit<'a> {
+        xl: Assignment<Fr>,
+        xr: Assignment<Fr>,
+        params: &'a MimcParams<Bls12>
+    }
+
+    impl<'a> Circuit<Bls12> for MyPermutationCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let xl = Num::alloc(cs, self.xl)?;
+            let xr = Num::alloc(cs, self.xr)?;
+
+            let (xl, xr) = mimc_permutation_gadget(cs, &xl.into(), &xr.into(), self.params)?;
+
+            Ok(PublicInputs::new().num(&xl).num(&xr))
+        }
+    }
+
+    for &exponent in &[5, 7] {
+        let constants = (0..20).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let params = MimcParams::new(exponent, constants.clone()).unwrap();
+
+        let (groth_params, ivk) = input::generate_input_parameters(MyPermutationCircuit {
+            xl: Assignment::unknown(),
+            xr: Assignment::unknown(),
+            params: &params
+        }, rng).unwrap();
+
+        for _ in 0..3 {
+            let xl: Fr = rng.gen();
+            let xr: Fr = rng.gen();
+
+            let (yl, yr) = mimc_permutation::<Bls12>(xl, xr, &params);
+            assert!((yl, yr) != (xl, xr));
+            assert_eq!(mimc_permutation_inverse::<Bls12>(yl, yr, &params), (xl, xr));
+
+            // A single round matches its definition.
+            let one_round = MimcParams::<Bls12>::new(exponent, constants[0..1].to_vec()).unwrap();
+            assert_eq!(mimc_permutation::<Bls12>(xl, xr, &one_round), ({
+                let mut tmp = xl;
+                tmp.add_assign(&constants[0]);
+                let mut acc = Fr::one();
+                for _ in 0..exponent {
+                    acc.mul_assign(&tmp);
+                }
+                acc.add_assign(&xr);
+                acc
+            }, xl));
+
+            let proof = create_random_proof::<Bls12, _, _, _>(MyPermutationCircuit {
+                xl: Assignment::known(xl),
+                xr: Assignment::known(xr),
+                params: &params
+            }, &groth_params, rng).unwrap();
+
+            assert!(input::verify_with_inputs(&ivk, &proof, &[yl, yr]).unwrap());
+            assert!(!input::verify_with_inputs(&ivk, &proof, &[yr, yl]).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_mimc_sponge() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{Rng, thread_rng};
+    use input;
+
+    let rng = &mut thread_rng();
+    let params = MimcParams::<Bls12>::default();
+
+    let n = |s: &str| Fr::from_str(s).unwrap();
+
+    // Test vectors for the default parameters.
+    assert_eq!(
+        mimc_sponge(&[], &params),
+        n("45300359949538012665540984976795114124522148041464516945944410513724943129998")
+    );
+    assert_eq!(
+        mimc_sponge(&[n("0")], &params),
+        n("38325338382533943277410886749270820853225782665958512894639397654428109892606")
+    );
+    assert_eq!(
+        mimc_sponge(&[n("0"), n("0")], &params),
+        n("46342506324446359804483986400049896729359583594317799621392372176771413344818")
+    );
+    assert_eq!(
+        mimc_sponge(&[n("1"), n("2"), n("3")], &params),
+        n("43114817877998823301700368272876150977143695753568503106372992581799562366445")
+    );
+
+    // The padding sets a single input apart from its padded form.
+    assert!(mimc_sponge(&[n("5")], &params) != mimc_sponge(&[n("5"), n("1")], &params));
+
+    struct MySpongeCircuit<'a> {
+        inputs: Vec<Assignment<Fr>>,
+        params: &'a MimcParams<Bls12>
+    }
+
+    impl<'a> Circuit<Bls12> for MySpongeCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut inputs = vec![];
+            for x in self.inputs {
+                inputs.push(Num::alloc(cs, x)?);
+            }
+
+            let hash = mimc_sponge_gadget(cs, &inputs, self.params)?;
+
+            Ok(PublicInputs::new().num(&hash))
+        }
+    }
+
+    for &len in &[0, 3] {
+        let (groth_params, ivk) = input::generate_input_parameters(MySpongeCircuit {
+            inputs: vec![Assignment::unknown(); len],
+            params: &params
+        }, rng).unwrap();
+
+        let inputs = (0..len).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let expected = mimc_sponge(&inputs, &params);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MySpongeCircuit {
+            inputs: inputs.iter().map(|&x| Assignment::known(x)).collect(),
+            params: &params
+        }, &groth_params, rng).unwrap();
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[expected]).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[rng.gen()]).unwrap());
+    }
+}
+
+#[test]
+fn test_mimc_cipher() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{Rng, thread_rng};
+    use input;
+
+    let rng = &mut thread_rng();
+    let params = MimcParams::<Bls12>::default();
+
+    let n = |s: &str| Fr::from_str(s).unwrap();
+
+    assert_eq!(
+        exponent_inverse::<Bls12>(5),
+        n("20974350070050476191779096203274386335076221000211055129041463479975432473805").into_repr().as_ref().to_vec()
+    );
+
+    // Test vectors for the default parameters.
+    assert_eq!(
+        mimc_encrypt(n("1"), n("2"), &params),
+        n("35539223654017759279852535767090669199167250880196701371132097585228191903870")
+    );
+    assert_eq!(
+        mimc_ctr_encrypt(n("1"), n("7"), &[n("3"), n("4")], &params),
+        vec![
+            n("31941191712818131652769867092718128984650019443417730417878780510862706692624"),
+            n("5458981841684030529446283401677154066652851216957359688140004884025054234383")
+        ]
+    );
+
+    for &exponent in &[5, 7] {
+        let params = MimcParams::<Bls12>::rand(rng, exponent).unwrap();
+
+        let key: Fr = rng.gen();
+        let plaintext: Fr = rng.gen();
+        let ciphertext = mimc_encrypt(key, plaintext, &params);
+
+        assert!(ciphertext != plaintext);
+        assert_eq!(mimc_decrypt(key, ciphertext, &params), plaintext);
+        assert!(mimc_decrypt(rng.gen(), ciphertext, &params) != plaintext);
+
+        let nonce: Fr = rng.gen();
+        let plaintext = (0..4).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let ciphertext = mimc_ctr_encrypt(key, nonce, &plaintext, &params);
+
+        assert_eq!(mimc_ctr_decrypt(key, nonce, &ciphertext, &params), plaintext);
+
+        // Adjacent nonces don't share key stream elements.
+        let mut next = nonce;
+        next.add_assign(&Fr::one());
+        let zeros = vec![Fr::zero(); 2];
+        assert!(mimc_ctr_encrypt(key, next, &zeros, &params)[0] != mimc_ctr_encrypt(key, nonce, &zeros, &params)[1]);
+    }
+
+    // Proves that the ciphertexts are encryptions under the key
+    // committed to with the sponge.
+    struct MyCipherCircuit<'a> {
+        commitment: Assignment<Fr>,
+        key: Assignment<Fr>,
+        nonce: Assignment<Fr>,
+        plaintext: Vec<Assignment<Fr>>,
+        params: &'a MimcParams<Bls12>
+    }
+
+    impl<'a> Circuit<Bls12> for MyCipherCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let commitment = Num::alloc(cs, self.commitment)?;
+            let key = Num::alloc(cs, self.key)?;
+            let nonce = Num::alloc(cs, self.nonce)?;
+
+            let mut plaintext = vec![];
+            for m in self.plaintext {
+                plaintext.push(Num::alloc(cs, m)?);
+            }
+
+            let block = mimc_encrypt_gadget(cs, &key, &plaintext[0], self.params)?;
+            let ciphertext = mimc_committed_ctr_encrypt_gadget(
+                cs,
+                &commitment,
+                &key,
+                &nonce,
+                &plaintext,
+                self.params
+            )?;
+
+            let mut inputs = PublicInputs::new().num(&commitment).num(&block).num(&nonce);
+            for c in &ciphertext {
+                inputs = inputs.num(c);
+            }
+
+            Ok(inputs)
+        }
+    }
+
+    let (groth_params, ivk) = input::generate_input_parameters(MyCipherCircuit {
+        commitment: Assignment::unknown(),
+        key: Assignment::unknown(),
+        nonce: Assignment::unknown(),
+        plaintext: vec![Assignment::unknown(); 2],
+        params: &params
+    }, rng).unwrap();
+
+    let key: Fr = rng.gen();
+    let nonce: Fr = rng.gen();
+    let plaintext = (0..2).map(|_| rng.gen()).collect::<Vec<Fr>>();
+
+    let prove = |commitment: Fr, rng: &mut ::rand::ThreadRng| {
+        create_random_proof::<Bls12, _, _, _>(MyCipherCircuit {
+            commitment: Assignment::known(commitment),
+            key: Assignment::known(key),
+            nonce: Assignment::known(nonce),
+            plaintext: plaintext.iter().map(|&m| Assignment::known(m)).collect(),
+            params: &params
+        }, &groth_params, rng).unwrap()
+    };
+
+    let proof = prove(mimc_sponge(&[key], &params), rng);
+
+    let mut inputs = vec![
+        mimc_sponge(&[key], &params),
+        mimc_encrypt(key, plaintext[0], &params),
+        nonce
+    ];
+    inputs.extend(mimc_ctr_encrypt(key, nonce, &plaintext, &params));
+
+    assert!(input::verify_with_inputs(&ivk, &proof, &inputs).unwrap());
+
+    // A different key gives different ciphertexts.
+    let other: Fr = rng.gen();
+    let mut wrong = inputs.clone();
+    wrong[1] = mimc_encrypt(other, plaintext[0], &params);
+    assert!(!input::verify_with_inputs(&ivk, &proof, &wrong).unwrap());
+
+    // The ciphertexts can't be claimed under another key's
+    // commitment.
+    let commitment = mimc_sponge(&[other], &params);
+    let proof = prove(commitment, rng);
+    inputs[0] = commitment;
+    assert!(!input::verify_with_inputs(&ivk, &proof, &inputs).unwrap());
+}
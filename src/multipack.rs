@@ -0,0 +1,189 @@
+//! Packing of bit strings into as few public inputs as possible.
+//!
+//! Bits are split into chunks of `E::Fr::capacity()` bits, and each
+//! chunk is exposed as the field element it represents in
+//! little-endian order. A 512-bit string costs three inputs
+//! over BLS12-381 rather than 512.
+
+use pairing::*;
+use bellman::*;
+
+use super::{Bit, LcNum};
+
+/// The number of bits packed into each input.
+pub fn chunk_size<E: Engine>() -> usize {
+    E::Fr::capacity() as usize
+}
+
+/// Exposes `bits` as multipacked public inputs. Costs one
+/// constraint per input.
+pub fn pack_into_inputs<E, CS>(
+    cs: &mut CS,
+    bits: &[Bit]
+) -> Result<(), Error>
+    where E: Engine, CS: PublicConstraintSystem<E>
+{
+    for chunk in bits.chunks(chunk_size::<E>()) {
+        LcNum::from_bits_le(chunk).inputize(cs)?;
+    }
+
+    Ok(())
+}
+
+/// Computes the inputs `pack_into_inputs` exposes for `bits`.
+pub fn compute_multipacking<E: Engine>(bits: &[bool]) -> Vec<E::Fr> {
+    let mut result = vec![];
+
+    for chunk in bits.chunks(chunk_size::<E>()) {
+        let mut cur = E::Fr::zero();
+        let mut coeff = E::Fr::one();
+
+        for bit in chunk {
+            if *bit {
+                cur.add_assign(&coeff);
+            }
+
+            coeff.double();
+        }
+
+        result.push(cur);
+    }
+
+    result
+}
+
+#[test]
+fn test_multipacking() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{Rng, thread_rng};
+    use super::Assignment;
+    use input::{self, InputError, PublicInputs};
+
+    let rng = &mut thread_rng();
+
+    struct MyPackingCircuit {
+        bits: Vec<Assignment<bool>>
+    }
+
+    impl Circuit<Bls12> for MyPackingCircuit {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut bits = Vec::with_capacity(512);
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            Ok(PublicInputs::new().bit(&bits[0]).packed_bits(&bits))
+        }
+    }
+
+    let (params, ivk) = input::generate_input_parameters(MyPackingCircuit {
+        bits: (0..512).map(|_| Assignment::unknown()).collect()
+    }, rng).unwrap();
+
+    assert_eq!(ivk.layout().len(), 4);
+
+    let bits = (0..512).map(|_| rng.gen()).collect::<Vec<bool>>();
+
+    let proof = create_random_proof::<Bls12, _, _, _>(MyPackingCircuit {
+        bits: bits.iter().map(|&b| Assignment::known(b)).collect()
+    }, &params, rng).unwrap();
+
+    let first = if bits[0] { Fr::one() } else { Fr::zero() };
+    let mut inputs = vec![first];
+    inputs.extend(compute_multipacking::<Bls12>(&bits));
+    assert_eq!(inputs.len(), 4);
+
+    assert!(input::verify_with_inputs(&ivk, &proof, &inputs).unwrap());
+
+    let mut wrong_bits = bits.clone();
+    wrong_bits[300] = !wrong_bits[300];
+    let mut wrong_inputs = vec![first];
+    wrong_inputs.extend(compute_multipacking::<Bls12>(&wrong_bits));
+
+    assert!(!input::verify_with_inputs(&ivk, &proof, &wrong_inputs).unwrap());
+
+    inputs[0] = Fr::from_str("2").unwrap();
+
+    match input::verify_with_inputs(&ivk, &proof, &inputs) {
+        Err(InputError::NotABit { index: 0 }) => {},
+        _ => panic!("expected a non-boolean bit input")
+    }
+}
+
+#[test]
+fn test_pack_into_inputs() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::Bls12;
+    use rand::{Rng, thread_rng};
+    use super::{Assignment, Num};
+    use input::PublicInputs;
+
+    let rng = &mut thread_rng();
+
+    struct MyPackedInputs {
+        bits: Vec<Bit>
+    }
+
+    impl<E: Engine> Input<E> for MyPackedInputs {
+        fn synthesize<CS: PublicConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), Error>
+        {
+            pack_into_inputs(cs, &self.bits)
+        }
+    }
+
+    struct MyPackingCircuit {
+        bits: Vec<Assignment<bool>>
+    }
+
+    impl Circuit<Bls12> for MyPackingCircuit {
+        type InputMap = MyPackedInputs;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut bits = Vec::with_capacity(self.bits.len());
+            for b in self.bits {
+                bits.push(Bit::alloc(cs, b)?);
+            }
+
+            Ok(MyPackedInputs { bits: bits })
+        }
+    }
+
+    // Verifies against plain numbers, so that the inputs must be
+    // exactly those compute_multipacking gives.
+    let verify = |pvk: &PreparedVerifyingKey<Bls12>, proof: &Proof<Bls12>, bits: &[bool]| {
+        verify_proof(pvk, proof, |cs| {
+            let mut map = PublicInputs::new();
+            for value in compute_multipacking::<Bls12>(bits) {
+                map = map.num(&Num::alloc(cs, Assignment::known(value))?);
+            }
+
+            Ok(map)
+        }).unwrap()
+    };
+
+    // Lengths below, at and just above a chunk, and a 512-bit
+    // string.
+    for &len in &[1, 254, 255, 512] {
+        let params = generate_random_parameters::<Bls12, _, _>(MyPackingCircuit {
+            bits: vec![Assignment::unknown(); len]
+        }, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let bits = (0..len).map(|_| rng.gen()).collect::<Vec<bool>>();
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyPackingCircuit {
+            bits: bits.iter().map(|&b| Assignment::known(b)).collect()
+        }, &params, rng).unwrap();
+
+        assert!(verify(&pvk, &proof, &bits));
+
+        let mut wrong = bits.clone();
+        wrong[len - 1] = !wrong[len - 1];
+        assert!(!verify(&pvk, &proof, &wrong));
+    }
+}
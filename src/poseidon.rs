@@ -0,0 +1,368 @@
+//! The Poseidon permutation and sponge over the BLS12-381 scalar
+//! field, natively and as a gadget.
+//!
+//! The state has `WIDTH` elements. Each round adds a round
+//! constant to every element, applies the `x^5` S-box and mixes
+//! the state with an MDS matrix. The first and last
+//! `FULL_ROUNDS / 2` rounds apply the S-box to every element;
+//! the `PARTIAL_ROUNDS` in between only to the first.
+//!
+//! The hash is the sponge in `sponge`, over this permutation.
+
+use pairing::*;
+use pairing::bls12_381::{Bls12, Fr};
+use bellman::*;
+
+use super::{Assignment, LcNum, Num};
+use blake2s::hash_to_field;
+use input::PublicInputs;
+use sponge::{self, cauchy_mds};
+
+pub use sponge::{RATE, WIDTH};
+
+pub const FULL_ROUNDS: usize = 8;
+pub const PARTIAL_ROUNDS: usize = 57;
+
+/// The domain `PoseidonParams::default()` derives its round
+/// constants from.
+pub const DEFAULT_DOMAIN: &'static [u8] = b"jubjub_poseidon_default";
+
+/// The round constants, `WIDTH` for each round, and the MDS
+/// matrix.
+#[derive(Clone)]
+pub struct PoseidonParams {
+    constants: Vec<Fr>,
+    mds: [[Fr; WIDTH]; WIDTH]
+}
+
+impl PoseidonParams {
+    /// Derives the round constants from `domain`, constant `i`
+    /// being `hash_to_field(domain, i)`. The MDS matrix is
+    /// `sponge::cauchy_mds()`.
+    pub fn from_seed(domain: &[u8]) -> PoseidonParams {
+        let constants = (0..(FULL_ROUNDS + PARTIAL_ROUNDS) * WIDTH).map(|i| {
+            hash_to_field::<Bls12>(domain, i as u32)
+        }).collect();
+
+        PoseidonParams {
+            constants: constants,
+            mds: cauchy_mds()
+        }
+    }
+
+    pub fn constants(&self) -> &[Fr] {
+        &self.constants
+    }
+
+    pub fn mds(&self) -> &[[Fr; WIDTH]; WIDTH] {
+        &self.mds
+    }
+}
+
+impl Default for PoseidonParams {
+    /// The parameters derived from `DEFAULT_DOMAIN`.
+    fn default() -> PoseidonParams {
+        PoseidonParams::from_seed(DEFAULT_DOMAIN)
+    }
+}
+
+fn is_full_round(round: usize) -> bool {
+    round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + PARTIAL_ROUNDS
+}
+
+/// Applies the permutation to `state`.
+pub fn poseidon_permutation(state: &mut [Fr; WIDTH], params: &PoseidonParams) {
+    for (round, constants) in params.constants.chunks(WIDTH).enumerate() {
+        for (s, c) in state.iter_mut().zip(constants) {
+            s.add_assign(c);
+        }
+
+        if is_full_round(round) {
+            for s in state.iter_mut() {
+                *s = s.pow(&[5]);
+            }
+        } else {
+            state[0] = state[0].pow(&[5]);
+        }
+
+        let mut mixed = [Fr::zero(); WIDTH];
+        for (m, row) in mixed.iter_mut().zip(params.mds.iter()) {
+            for (s, entry) in state.iter().zip(row.iter()) {
+                let mut tmp = *s;
+                tmp.mul_assign(entry);
+                m.add_assign(&tmp);
+            }
+        }
+
+        *state = mixed;
+    }
+}
+
+/// Hashes any number of field elements with the sponge.
+pub fn poseidon_hash(inputs: &[Fr], params: &PoseidonParams) -> Fr {
+    sponge::sponge_hash(inputs, |state| poseidon_permutation(state, params))
+}
+
+/// Multiplies the state by the MDS matrix, in a circuit. Costs
+/// no constraints.
+fn mix_gadget(state: &[LcNum<Bls12>], params: &PoseidonParams) -> Vec<LcNum<Bls12>> {
+    params.mds.iter().map(|row| {
+        combine(state, row)
+    }).collect()
+}
+
+/// Sums `nums` weighted by `coeffs`, skipping zero weights.
+fn combine(nums: &[LcNum<Bls12>], coeffs: &[Fr]) -> LcNum<Bls12> {
+    let mut acc = LcNum::zero();
+
+    for (num, coeff) in nums.iter().zip(coeffs) {
+        if !coeff.is_zero() {
+            acc.add_assign(&num.scale(*coeff));
+        }
+    }
+
+    acc
+}
+
+/// A full round, in a circuit. Costs three constraints per
+/// element.
+fn full_round_gadget<CS>(
+    cs: &mut CS,
+    state: &[LcNum<Bls12>],
+    constants: &[Fr],
+    params: &PoseidonParams
+) -> Result<Vec<LcNum<Bls12>>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    let mut sboxes = Vec::with_capacity(WIDTH);
+    for (s, c) in state.iter().zip(constants) {
+        sboxes.push(s.add(&LcNum::constant::<CS>(*c)).pow(cs, 5)?);
+    }
+
+    Ok(mix_gadget(&sboxes, params))
+}
+
+/// The partial rounds, in a circuit. Only the first element goes
+/// through the S-box, so the state stays a linear function of
+/// one, the state entering these rounds and the S-box outputs.
+/// Mixing linear combinations directly would double their length
+/// every round, so the state is tracked as coefficients over
+/// those variables instead, and only turned back into linear
+/// combinations at the end. Costs three constraints per round.
+fn partial_rounds_gadget<CS>(
+    cs: &mut CS,
+    state: &[LcNum<Bls12>],
+    rounds: &[&[Fr]],
+    params: &PoseidonParams
+) -> Result<Vec<LcNum<Bls12>>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    let mut basis = vec![LcNum::constant::<CS>(Fr::one())];
+    basis.extend(state.iter().cloned());
+
+    let unit = |i: usize, len: usize| {
+        let mut coeffs = vec![Fr::zero(); len];
+        coeffs[i] = Fr::one();
+        coeffs
+    };
+
+    let mut coeffs = (0..WIDTH).map(|i| unit(i + 1, basis.len())).collect::<Vec<_>>();
+
+    for constants in rounds {
+        for (row, c) in coeffs.iter_mut().zip(constants.iter()) {
+            row[0].add_assign(c);
+        }
+
+        let sbox = combine(&basis, &coeffs[0]).pow(cs, 5)?;
+        basis.push(sbox);
+
+        for row in coeffs[1..].iter_mut() {
+            row.push(Fr::zero());
+        }
+        coeffs[0] = unit(basis.len() - 1, basis.len());
+
+        coeffs = params.mds.iter().map(|mds_row| {
+            (0..basis.len()).map(|k| {
+                let mut acc = Fr::zero();
+                for (entry, row) in mds_row.iter().zip(coeffs.iter()) {
+                    let mut tmp = row[k];
+                    tmp.mul_assign(entry);
+                    acc.add_assign(&tmp);
+                }
+
+                acc
+            }).collect()
+        }).collect();
+    }
+
+    Ok(coeffs.iter().map(|row| combine(&basis, row)).collect())
+}
+
+/// The same as `poseidon_permutation`, in a circuit. Each full
+/// round costs three constraints per element, and each partial
+/// round three for its one S-box.
+pub fn poseidon_permutation_gadget<CS>(
+    cs: &mut CS,
+    state: &[LcNum<Bls12>],
+    params: &PoseidonParams
+) -> Result<Vec<LcNum<Bls12>>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    assert_eq!(state.len(), WIDTH);
+
+    let rounds = params.constants.chunks(WIDTH).collect::<Vec<_>>();
+    let (first, rest) = rounds.split_at(FULL_ROUNDS / 2);
+    let (partial, last) = rest.split_at(PARTIAL_ROUNDS);
+
+    let mut state = state.to_vec();
+
+    for constants in first {
+        state = full_round_gadget(cs, &state, constants, params)?;
+    }
+
+    state = partial_rounds_gadget(cs, &state, partial, params)?;
+
+    for constants in last {
+        state = full_round_gadget(cs, &state, constants, params)?;
+    }
+
+    Ok(state)
+}
+
+/// The same as `poseidon_hash`, in a circuit. Costs a permutation
+/// for every `RATE` inputs, and one constraint for the result.
+pub fn poseidon_hash_gadget<CS>(
+    cs: &mut CS,
+    inputs: &[Num<Bls12>],
+    params: &PoseidonParams
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    sponge::sponge_hash_gadget(cs, inputs, |cs, state| poseidon_permutation_gadget(cs, state, params))
+}
+
+/// A demo circuit for proving knowledge of the preimage of a
+/// Poseidon hash of two elements, to compare with `MiMCDemo`.
+pub struct PoseidonDemo<'a> {
+    pub xl: Option<Fr>,
+    pub xr: Option<Fr>,
+    pub params: &'a PoseidonParams
+}
+
+impl<'a> Circuit<Bls12> for PoseidonDemo<'a> {
+    type InputMap = PublicInputs<Bls12>;
+
+    fn synthesize<CS: ConstraintSystem<Bls12>>(
+        self,
+        cs: &mut CS
+    ) -> Result<Self::InputMap, Error>
+    {
+        let xl = Num::alloc(cs, self.xl.map_or(Assignment::unknown(), Assignment::known))?;
+        let xr = Num::alloc(cs, self.xr.map_or(Assignment::unknown(), Assignment::known))?;
+
+        let image = poseidon_hash_gadget(cs, &[xl, xr], self.params)?;
+
+        Ok(PublicInputs::new().num(&image))
+    }
+}
+
+#[test]
+fn test_poseidon_vectors() {
+    let params = PoseidonParams::default();
+
+    let n = |s: &str| Fr::from_str(s).unwrap();
+
+    assert_eq!(params.constants().len(), 195);
+    assert_eq!(params.constants()[0], n("873114033725163307296159276295127816818369305464738968734724337142079143720"));
+
+    let mut state = [n("0"), n("1"), n("2")];
+    poseidon_permutation(&mut state, &params);
+    assert_eq!(state, [
+        n("21719113517742815574403505883989812415381809122932750752196564400289430328493"),
+        n("41789099448374959670263073406368117256908708803922600612774023481068540413396"),
+        n("15138607269052445438909821944886474305288059711019841234816242849930959937611")
+    ]);
+
+    assert_eq!(
+        poseidon_hash(&[], &params),
+        n("12387051741143705266092865697565748078554704648127737155055493690256179261946")
+    );
+    assert_eq!(
+        poseidon_hash(&[n("0")], &params),
+        n("13606559446105532512688982372489027114584634574103126999282410640744706880040")
+    );
+    assert_eq!(
+        poseidon_hash(&[n("1"), n("2")], &params),
+        n("49597748586450738242926382927781089528011902012500863979271292986061981247879")
+    );
+    assert_eq!(
+        poseidon_hash(&[n("1"), n("2"), n("3")], &params),
+        n("13496299340468080453962716761194823088740734187613243861526354334844225794997")
+    );
+
+    // Padding with zeros doesn't collide, since the length is
+    // absorbed into the capacity.
+    assert!(poseidon_hash(&[n("1")], &params) != poseidon_hash(&[n("1"), n("0")], &params));
+}
+
+#[test]
+fn test_poseidon_gadget() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input;
+    use super::ConstraintCounter;
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+    let params = PoseidonParams::default();
+
+    struct MyPoseidonCircuit<'a> {
+        inputs: Vec<Assignment<Fr>>,
+        params: &'a PoseidonParams,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyPoseidonCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut inputs = vec![];
+            for x in self.inputs {
+                inputs.push(Num::alloc(cs, x)?);
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let hash = poseidon_hash_gadget(&mut counter, &inputs, self.params)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(&hash))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    for &len in &[2, 3] {
+        let (groth_params, ivk) = input::generate_input_parameters(MyPoseidonCircuit {
+            inputs: vec![Assignment::unknown(); len],
+            params: &params,
+            num_constraints: &num_constraints
+        }, rng).unwrap();
+
+        let permutation = FULL_ROUNDS * WIDTH * 3 + PARTIAL_ROUNDS * 3;
+        assert_eq!(permutation, 243);
+        assert_eq!(num_constraints.get(), ((len + 1) / 2) * permutation + 1);
+
+        let inputs = (0..len).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let expected = poseidon_hash(&inputs, &params);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyPoseidonCircuit {
+            inputs: inputs.iter().map(|&x| Assignment::known(x)).collect(),
+            params: &params,
+            num_constraints: &num_constraints
+        }, &groth_params, rng).unwrap();
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[expected]).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[rng.gen()]).unwrap());
+    }
+}
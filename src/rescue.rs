@@ -0,0 +1,346 @@
+//! The Rescue-Prime permutation and sponge over the BLS12-381
+//! scalar field, natively and as a gadget.
+//!
+//! Each round applies the `x^5` S-box to every element, mixes
+//! the state with an MDS matrix and adds round constants, then
+//! does the same with the inverse S-box `x^(1/5)`. In a circuit
+//! the inverse S-box is as cheap as the forward one: the root is
+//! witnessed, and raising it to the fifth power is constrained
+//! to give the input back.
+//!
+//! The hash is the sponge in `sponge`, over this permutation, as
+//! for Poseidon.
+
+use pairing::*;
+use pairing::bls12_381::{Bls12, Fr};
+use bellman::*;
+
+use super::{Assignment, LcNum, Num, exponent_inverse};
+use blake2s::hash_to_field;
+use input::PublicInputs;
+use sponge::{self, cauchy_mds};
+
+pub use sponge::{RATE, WIDTH};
+
+pub const ALPHA: u64 = 5;
+
+/// The security level the number of rounds is chosen for.
+pub const SECURITY_BITS: usize = 128;
+
+/// The domain `RescueParams::default()` derives its round
+/// constants from.
+pub const DEFAULT_DOMAIN: &'static [u8] = b"jubjub_rescue_default";
+
+/// The round constants, `2 * WIDTH` for each round, and the MDS
+/// matrix.
+#[derive(Clone)]
+pub struct RescueParams {
+    constants: Vec<Fr>,
+    mds: [[Fr; WIDTH]; WIDTH],
+    alpha_inv: Vec<u64>
+}
+
+impl RescueParams {
+    /// Derives the round constants from `domain`, constant `i`
+    /// being `hash_to_field(domain, i)`, for `num_rounds()`
+    /// rounds. The MDS matrix is `sponge::cauchy_mds()`, as for
+    /// Poseidon.
+    pub fn from_seed(domain: &[u8]) -> RescueParams {
+        let constants = (0..2 * WIDTH * RescueParams::num_rounds()).map(|i| {
+            hash_to_field::<Bls12>(domain, i as u32)
+        }).collect();
+
+        RescueParams {
+            constants: constants,
+            mds: cauchy_mds(),
+            alpha_inv: exponent_inverse::<Bls12>(ALPHA)
+        }
+    }
+
+    /// The number of rounds recommended by Rescue-Prime: the
+    /// smallest number of rounds, but at least five, for which a
+    /// Gröbner basis attack on the sponge costs more than
+    /// `2^SECURITY_BITS`, plus half as many again.
+    pub fn num_rounds() -> usize {
+        // log2 of the binomial coefficient (n choose k).
+        let log2_binomial = |n: usize, k: usize| {
+            (1..k + 1).map(|i| ((n - k + i) as f64 / i as f64).log2()).sum::<f64>()
+        };
+
+        let mut rounds = 1;
+        loop {
+            let v = WIDTH * (rounds - 1) + RATE;
+            let degree = (ALPHA as usize - 1) * WIDTH * (rounds - 1) / 2 + 2;
+
+            if 2.0 * log2_binomial(v + degree, v) > SECURITY_BITS as f64 {
+                break;
+            }
+
+            rounds += 1;
+        }
+
+        (3 * ::std::cmp::max(5, rounds) + 1) / 2
+    }
+
+    pub fn constants(&self) -> &[Fr] {
+        &self.constants
+    }
+
+    pub fn mds(&self) -> &[[Fr; WIDTH]; WIDTH] {
+        &self.mds
+    }
+}
+
+impl Default for RescueParams {
+    /// The parameters derived from `DEFAULT_DOMAIN`.
+    fn default() -> RescueParams {
+        RescueParams::from_seed(DEFAULT_DOMAIN)
+    }
+}
+
+/// Mixes the state and adds the constants.
+fn mix(state: &mut [Fr; WIDTH], constants: &[Fr], params: &RescueParams) {
+    let mut mixed = [Fr::zero(); WIDTH];
+    for ((m, row), c) in mixed.iter_mut().zip(params.mds.iter()).zip(constants) {
+        for (s, entry) in state.iter().zip(row.iter()) {
+            let mut tmp = *s;
+            tmp.mul_assign(entry);
+            m.add_assign(&tmp);
+        }
+
+        m.add_assign(c);
+    }
+
+    *state = mixed;
+}
+
+/// Applies the permutation to `state`.
+pub fn rescue_permutation(state: &mut [Fr; WIDTH], params: &RescueParams) {
+    for constants in params.constants.chunks(2 * WIDTH) {
+        for s in state.iter_mut() {
+            *s = s.pow(&[ALPHA]);
+        }
+
+        mix(state, &constants[0..WIDTH], params);
+
+        for s in state.iter_mut() {
+            *s = s.pow(&params.alpha_inv);
+        }
+
+        mix(state, &constants[WIDTH..], params);
+    }
+}
+
+/// Hashes any number of field elements with the sponge.
+pub fn rescue_hash(inputs: &[Fr], params: &RescueParams) -> Fr {
+    sponge::sponge_hash(inputs, |state| rescue_permutation(state, params))
+}
+
+/// Witnesses `x^(1/5)` and constrains its fifth power to be `x`.
+/// Costs three constraints.
+fn inverse_sbox<CS>(
+    cs: &mut CS,
+    x: &LcNum<Bls12>,
+    params: &RescueParams
+) -> Result<LcNum<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    let root = Num::alloc(cs, match x.get_value() {
+        Assignment::Known(x) => Assignment::known(x.pow(&params.alpha_inv)),
+        Assignment::Unknown => Assignment::unknown()
+    })?;
+    let root = LcNum::from(root);
+
+    // root^4 * root = x
+    let root4 = root.square(cs)?.square(cs)?;
+
+    cs.enforce(
+        root4.lc(),
+        root.lc(),
+        x.lc()
+    );
+
+    Ok(root)
+}
+
+/// The same as `mix`, in a circuit. Costs no constraints.
+fn mix_gadget<CS>(
+    state: &[LcNum<Bls12>],
+    constants: &[Fr],
+    params: &RescueParams
+) -> Vec<LcNum<Bls12>>
+    where CS: ConstraintSystem<Bls12>
+{
+    params.mds.iter().zip(constants).map(|(row, c)| {
+        state.iter().zip(row.iter()).fold(LcNum::constant::<CS>(*c), |acc, (s, entry)| {
+            acc.add(&s.scale(*entry))
+        })
+    }).collect()
+}
+
+/// The same as `rescue_permutation`, in a circuit. Every S-box,
+/// forward or inverse, costs three constraints, so a round costs
+/// `6 * WIDTH`.
+pub fn rescue_permutation_gadget<CS>(
+    cs: &mut CS,
+    state: &[LcNum<Bls12>],
+    params: &RescueParams
+) -> Result<Vec<LcNum<Bls12>>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    assert_eq!(state.len(), WIDTH);
+
+    let mut state = state.to_vec();
+
+    for constants in params.constants.chunks(2 * WIDTH) {
+        for s in state.iter_mut() {
+            *s = s.pow(cs, ALPHA)?;
+        }
+
+        state = mix_gadget::<CS>(&state, &constants[0..WIDTH], params);
+
+        for s in state.iter_mut() {
+            *s = inverse_sbox(cs, s, params)?;
+        }
+
+        state = mix_gadget::<CS>(&state, &constants[WIDTH..], params);
+    }
+
+    Ok(state)
+}
+
+/// The same as `rescue_hash`, in a circuit. Costs a permutation
+/// for every `RATE` inputs, and one constraint for the result.
+pub fn rescue_hash_gadget<CS>(
+    cs: &mut CS,
+    inputs: &[Num<Bls12>],
+    params: &RescueParams
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    sponge::sponge_hash_gadget(cs, inputs, |cs, state| rescue_permutation_gadget(cs, state, params))
+}
+
+/// A demo circuit for proving knowledge of the preimage of a
+/// Rescue-Prime hash of two elements, to compare with `MiMCDemo`
+/// and `PoseidonDemo`.
+pub struct RescueDemo<'a> {
+    pub xl: Option<Fr>,
+    pub xr: Option<Fr>,
+    pub params: &'a RescueParams
+}
+
+impl<'a> Circuit<Bls12> for RescueDemo<'a> {
+    type InputMap = PublicInputs<Bls12>;
+
+    fn synthesize<CS: ConstraintSystem<Bls12>>(
+        self,
+        cs: &mut CS
+    ) -> Result<Self::InputMap, Error>
+    {
+        let xl = Num::alloc(cs, self.xl.map_or(Assignment::unknown(), Assignment::known))?;
+        let xr = Num::alloc(cs, self.xr.map_or(Assignment::unknown(), Assignment::known))?;
+
+        let image = rescue_hash_gadget(cs, &[xl, xr], self.params)?;
+
+        Ok(PublicInputs::new().num(&image))
+    }
+}
+
+#[test]
+fn test_rescue_vectors() {
+    let params = RescueParams::default();
+
+    let n = |s: &str| Fr::from_str(s).unwrap();
+
+    assert_eq!(RescueParams::num_rounds(), 14);
+    assert_eq!(params.constants().len(), 2 * WIDTH * 14);
+    assert_eq!(params.constants()[0], n("29484134514568235288446687094054720950036610078888419656100482267284082178807"));
+
+    let mut state = [n("0"), n("1"), n("2")];
+    rescue_permutation(&mut state, &params);
+    assert_eq!(state, [
+        n("21177200485731451141542520505079017728310690513327103093547274543682430027088"),
+        n("30171262501153636746641482755369469627232229298468441486501107781024044252629"),
+        n("40226452616330138063760844098628734060038305475916525043592534709699296358869")
+    ]);
+
+    assert_eq!(
+        rescue_hash(&[], &params),
+        n("3203854434443249064259765755696395749528378261033030121628405009317424237040")
+    );
+    assert_eq!(
+        rescue_hash(&[n("0")], &params),
+        n("27735332298691517942935512266594308977829715109805755430121675321688304755652")
+    );
+    assert_eq!(
+        rescue_hash(&[n("1"), n("2")], &params),
+        n("29135134558705114187002865143996364169226823793533559037167368506229132162495")
+    );
+    assert_eq!(
+        rescue_hash(&[n("1"), n("2"), n("3")], &params),
+        n("15370354745371967420663643620739582450266604780548327323508912855425359450443")
+    );
+}
+
+#[test]
+fn test_rescue_gadget() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input;
+    use super::ConstraintCounter;
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+    let params = RescueParams::default();
+
+    struct MyRescueCircuit<'a> {
+        inputs: Vec<Assignment<Fr>>,
+        params: &'a RescueParams,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyRescueCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut inputs = vec![];
+            for x in self.inputs {
+                inputs.push(Num::alloc(cs, x)?);
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let hash = rescue_hash_gadget(&mut counter, &inputs, self.params)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(&hash))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    for &len in &[2, 3] {
+        let (groth_params, ivk) = input::generate_input_parameters(MyRescueCircuit {
+            inputs: vec![Assignment::unknown(); len],
+            params: &params,
+            num_constraints: &num_constraints
+        }, rng).unwrap();
+
+        let permutation = 14 * 6 * WIDTH;
+        assert_eq!(permutation, 252);
+        assert_eq!(num_constraints.get(), ((len + 1) / 2) * permutation + 1);
+
+        let inputs = (0..len).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let expected = rescue_hash(&inputs, &params);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyRescueCircuit {
+            inputs: inputs.iter().map(|&x| Assignment::known(x)).collect(),
+            params: &params,
+            num_constraints: &num_constraints
+        }, &groth_params, rng).unwrap();
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[expected]).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[rng.gen()]).unwrap());
+    }
+}
@@ -0,0 +1,346 @@
+//! The SHA-256 hash function, as specified in FIPS 180-4,
+//! natively and as a gadget.
+//!
+//! The gadget works on bits in the order the standard writes
+//! them: each byte is eight booleans, most significant first, so
+//! that words are read big-endian just by concatenating bytes.
+
+use pairing::*;
+use bellman::*;
+
+use super::{Bit, Boolean};
+use uint32::UInt32;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+/// Compresses a 64-byte block into the state.
+fn compress(h: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for i in 0..16 {
+        let bytes = &block[i * 4..i * 4 + 4];
+        w[i] = ((bytes[0] as u32) << 24) |
+               ((bytes[1] as u32) << 16) |
+               ((bytes[2] as u32) << 8) |
+               (bytes[3] as u32);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+    }
+
+    let mut v = *h;
+
+    for i in 0..64 {
+        let (a, b, c, d, e, f, g, hh) = (v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]);
+
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        v = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+    }
+
+    for (h, v) in h.iter_mut().zip(v.iter()) {
+        *h = h.wrapping_add(*v);
+    }
+}
+
+/// Hashes `input` to a 32-byte digest.
+pub fn sha256(input: &[u8]) -> [u8; 32] {
+    let mut padded = input.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    for i in (0..8).rev() {
+        padded.push(((input.len() as u64 * 8) >> (i * 8)) as u8);
+    }
+
+    let mut h = IV;
+    for block in padded.chunks(64) {
+        compress(&mut h, block);
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, word) in h.iter().enumerate() {
+        for j in 0..4 {
+            digest[i * 4 + j] = (word >> (24 - j * 8)) as u8;
+        }
+    }
+
+    digest
+}
+
+/// Computes `x >>> a ^ x >>> b ^ x >>> c`, or `x >> c` in place
+/// of the last rotation when `shift` is set. Costs two
+/// constraints per bit, or one for the bits the shift clears.
+fn sigma<E, CS>(cs: &mut CS, x: &UInt32, (a, b, c): (usize, usize, usize), shift: bool) -> Result<UInt32, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let last = if shift { x.shr(c) } else { x.rotr(c) };
+
+    x.rotr(a).xor(cs, &x.rotr(b))?.xor(cs, &last)
+}
+
+/// The same as `compress`, in a circuit, where `block` is 512
+/// bits. Costs 26,416 constraints when `h` and `block` are
+/// witnessed: 150 for each of the 48 words of the message
+/// schedule past the block, 296 for each of the 64 rounds and 34
+/// for each of the eight final additions.
+pub fn sha256_compression_gadget<E, CS>(
+    cs: &mut CS,
+    h: &[UInt32],
+    block: &[Boolean]
+) -> Result<Vec<UInt32>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    assert_eq!(h.len(), 8);
+    assert_eq!(block.len(), 512);
+
+    let mut w = block.chunks(32).map(UInt32::from_bits_be).collect::<Vec<_>>();
+    for i in 16..64 {
+        let s0 = sigma(cs, &w[i - 15], (7, 18, 3), true)?;
+        let s1 = sigma(cs, &w[i - 2], (17, 19, 10), true)?;
+        let wi = UInt32::addmany(cs, &[w[i - 16].clone(), s0, w[i - 7].clone(), s1])?;
+        w.push(wi);
+    }
+
+    let mut v = h.to_vec();
+
+    for i in 0..64 {
+        let s1 = sigma(cs, &v[4], (6, 11, 25), false)?;
+        let ch = UInt32::sha256_ch(cs, &v[4], &v[5], &v[6])?;
+        let s0 = sigma(cs, &v[0], (2, 13, 22), false)?;
+        let maj = UInt32::sha256_maj(cs, &v[0], &v[1], &v[2])?;
+
+        // Summing `t1` into both new words, rather than unpacking
+        // it first, saves its 32 bits.
+        let t1 = [v[7].clone(), s1, ch, UInt32::constant(K[i]), w[i].clone()];
+
+        let mut a = t1.to_vec();
+        a.push(s0);
+        a.push(maj);
+        let a = UInt32::addmany(cs, &a)?;
+
+        let mut e = t1.to_vec();
+        e.push(v[3].clone());
+        let e = UInt32::addmany(cs, &e)?;
+
+        v = vec![a, v[0].clone(), v[1].clone(), v[2].clone(), e, v[4].clone(), v[5].clone(), v[6].clone()];
+    }
+
+    let mut result = Vec::with_capacity(8);
+    for (h, v) in h.iter().zip(v.into_iter()) {
+        result.push(UInt32::addmany(cs, &[h.clone(), v])?);
+    }
+
+    Ok(result)
+}
+
+/// The same as `sha256`, in a circuit, for an input of any number
+/// of bits. The padding depends only on the length, so it costs
+/// no constraints. Returns the 256 bits of the digest.
+pub fn sha256_gadget<E, CS>(
+    cs: &mut CS,
+    input: &[Bit]
+) -> Result<Vec<Boolean>, Error>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let mut padded = input.iter().map(|&b| Boolean::from_bit(b)).collect::<Vec<_>>();
+    padded.push(Boolean::Constant(true));
+    while padded.len() % 512 != 448 {
+        padded.push(Boolean::Constant(false));
+    }
+    for i in (0..64).rev() {
+        padded.push(Boolean::Constant(((input.len() as u64) >> i) & 1 == 1));
+    }
+
+    let mut h = IV.iter().map(|&w| UInt32::constant(w)).collect::<Vec<_>>();
+    for block in padded.chunks(512) {
+        h = sha256_compression_gadget(cs, &h, block)?;
+    }
+
+    Ok(h.into_iter().flat_map(|w| w.into_bits_be()).collect())
+}
+
+#[test]
+fn test_sha256() {
+    fn hex(digest: [u8; 32]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // The examples from FIPS 180-4.
+    assert_eq!(hex(sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(hex(sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(
+        hex(sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(hex(sha256(&vec![b'a'; 1000000])), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+}
+
+#[test]
+fn test_sha256_gadget() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::Bls12;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use multipack;
+    use super::{Assignment, ConstraintCounter};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+
+    fn bits_of(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1)).collect()
+    }
+
+    struct MySha256Circuit<'a> {
+        input: Vec<Assignment<bool>>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MySha256Circuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut input = vec![];
+            for b in self.input {
+                input.push(Bit::alloc(cs, b)?);
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let digest = sha256_gadget(&mut counter, &input)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().packed_booleans::<CS>(&digest))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    // The second input fills a block, so its padding takes a block
+    // of its own.
+    for &(len, expected_constraints) in &[(3, 24591), (64, 45280)] {
+        let (params, ivk) = input::generate_input_parameters(MySha256Circuit {
+            input: vec![Assignment::unknown(); len * 8],
+            num_constraints: &num_constraints
+        }, rng).unwrap();
+
+        assert_eq!(num_constraints.get(), expected_constraints);
+
+        let data = (0..len).map(|_| rng.gen()).collect::<Vec<u8>>();
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MySha256Circuit {
+            input: bits_of(&data).into_iter().map(Assignment::known).collect(),
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        let expected = multipack::compute_multipacking::<Bls12>(&bits_of(&sha256(&data)));
+        assert!(input::verify_with_inputs(&ivk, &proof, &expected).unwrap());
+
+        let mut wrong = data.clone();
+        wrong[0] ^= 1;
+        let wrong = multipack::compute_multipacking::<Bls12>(&bits_of(&sha256(&wrong)));
+        assert!(!input::verify_with_inputs(&ivk, &proof, &wrong).unwrap());
+    }
+}
+
+#[test]
+fn test_sha256_compression_gadget() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::Bls12;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use multipack;
+    use super::{Assignment, ConstraintCounter};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+
+    fn bits_of(words: &[u32]) -> Vec<bool> {
+        words.iter().flat_map(|&w| (0..32).rev().map(move |i| (w >> i) & 1 == 1)).collect()
+    }
+
+    struct MyCompressionCircuit<'a> {
+        h: Vec<Assignment<u32>>,
+        block: Vec<Assignment<bool>>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyCompressionCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut h = vec![];
+            for w in self.h {
+                h.push(UInt32::alloc(cs, w)?);
+            }
+
+            let mut block = vec![];
+            for b in self.block {
+                block.push(Boolean::from_bit(Bit::alloc(cs, b)?));
+            }
+
+            let mut counter = ConstraintCounter::new(cs);
+            let h = sha256_compression_gadget(&mut counter, &h, &block)?;
+            self.num_constraints.set(counter.num_constraints);
+
+            let bits = h.into_iter().flat_map(|w| w.into_bits_be()).collect::<Vec<_>>();
+
+            Ok(PublicInputs::new().packed_booleans::<CS>(&bits))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let (params, ivk) = input::generate_input_parameters(MyCompressionCircuit {
+        h: vec![Assignment::unknown(); 8],
+        block: vec![Assignment::unknown(); 512],
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+
+    assert_eq!(num_constraints.get(), 26416);
+
+    let mut h = [0u32; 8];
+    for w in h.iter_mut() {
+        *w = rng.gen();
+    }
+    let block = (0..64).map(|_| rng.gen()).collect::<Vec<u8>>();
+    let block_bits = block.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1)).collect::<Vec<_>>();
+
+    let proof = create_random_proof::<Bls12, _, _, _>(MyCompressionCircuit {
+        h: h.iter().map(|&w| Assignment::known(w)).collect(),
+        block: block_bits.into_iter().map(Assignment::known).collect(),
+        num_constraints: &num_constraints
+    }, &params, rng).unwrap();
+
+    let mut expected = h;
+    compress(&mut expected, &block);
+    assert!(input::verify_with_inputs(&ivk, &proof, &multipack::compute_multipacking::<Bls12>(&bits_of(&expected))).unwrap());
+
+    let mut wrong = h;
+    wrong[0] ^= 1;
+    compress(&mut wrong, &block);
+    assert!(!input::verify_with_inputs(&ivk, &proof, &multipack::compute_multipacking::<Bls12>(&bits_of(&wrong))).unwrap());
+}
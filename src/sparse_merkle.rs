@@ -0,0 +1,520 @@
+//! A sparse Merkle tree keyed by 256-bit paths, natively and as a
+//! gadget, for proving that a key is present in a set or absent
+//! from it, such as a nullifier which hasn't been spent.
+//!
+//! The tree has a leaf for every key, read from the root down, most
+//! significant bit first, so it is always `DEPTH` levels deep and
+//! no two keys share a leaf. Absent keys have the leaf `Fr::zero()`,
+//! so the roots of empty subtrees are precomputed and only the
+//! nodes above present keys are stored. A path is the siblings from
+//! the leaf up to the root.
+
+use pairing::*;
+use pairing::bls12_381::{Bls12, Fr};
+use bellman::*;
+
+use std::collections::HashMap;
+
+use super::{Bit, JubJub, LcNum, Num};
+use merkle::{merkle_hash_native, merkle_hash_gadget};
+use mimc::{MimcParams, mimc_sponge, mimc_sponge_gadget};
+
+/// The depth of every tree, which is the number of bits in a key.
+pub const DEPTH: usize = 256;
+
+/// The hash a tree combines its children with.
+#[derive(Clone, Copy)]
+pub enum SparseMerkleHash<'a> {
+    /// The same hash as `merkle::merkle_hash_native`.
+    Pedersen {
+        generators: &'a [(Vec<Fr>, Vec<Fr>)],
+        j: &'a JubJub
+    },
+    /// The MiMC sponge of the two children.
+    Mimc(&'a MimcParams<Bls12>)
+}
+
+impl<'a> SparseMerkleHash<'a> {
+    pub fn hash(&self, left: &Fr, right: &Fr) -> Fr {
+        match *self {
+            SparseMerkleHash::Pedersen { generators, j } => merkle_hash_native(left, right, generators, j),
+            SparseMerkleHash::Mimc(params) => mimc_sponge(&[*left, *right], params)
+        }
+    }
+
+    /// The same as `hash`, in a circuit.
+    pub fn hash_gadget<CS>(
+        &self,
+        cs: &mut CS,
+        left: &LcNum<Bls12>,
+        right: &LcNum<Bls12>
+    ) -> Result<Num<Bls12>, Error>
+        where CS: ConstraintSystem<Bls12>
+    {
+        match *self {
+            SparseMerkleHash::Pedersen { generators, j } => merkle_hash_gadget(cs, left, right, generators, j),
+            SparseMerkleHash::Mimc(params) => mimc_sponge_gadget(cs, &[left.clone(), right.clone()], params)
+        }
+    }
+}
+
+/// The bits of `key`, from the root down.
+pub fn key_bits(key: &[u8; 32]) -> Vec<bool> {
+    key.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1)).collect()
+}
+
+/// Computes the root of a tree in which the leaf at `key_bits` is
+/// `leaf`, with the siblings `path`.
+pub fn sparse_merkle_root_native(
+    key_bits: &[bool],
+    leaf: &Fr,
+    path: &[Fr],
+    hash: &SparseMerkleHash
+) -> Fr
+{
+    assert_eq!(key_bits.len(), DEPTH);
+    assert_eq!(path.len(), DEPTH);
+
+    key_bits.iter().rev().zip(path.iter()).fold(*leaf, |cur, (&is_right, sibling)| {
+        if is_right {
+            hash.hash(sibling, &cur)
+        } else {
+            hash.hash(&cur, sibling)
+        }
+    })
+}
+
+/// A sparse Merkle tree with a leaf for every 256-bit key.
+pub struct SparseMerkleTree<'a> {
+    hash: SparseMerkleHash<'a>,
+    empty_roots: Vec<Fr>,
+    // The nodes which aren't the roots of empty subtrees, by height
+    // and by the bits of the path to them from the root.
+    nodes: HashMap<(usize, Vec<bool>), Fr>
+}
+
+impl<'a> SparseMerkleTree<'a> {
+    pub fn new(hash: SparseMerkleHash<'a>) -> SparseMerkleTree<'a> {
+        let mut empty_roots = vec![Fr::zero()];
+        for h in 0..DEPTH {
+            let empty = empty_roots[h];
+            empty_roots.push(hash.hash(&empty, &empty));
+        }
+
+        SparseMerkleTree {
+            hash: hash,
+            empty_roots: empty_roots,
+            nodes: HashMap::new()
+        }
+    }
+
+    /// The root of a subtree of height `height` with no present
+    /// keys.
+    pub fn empty_root(&self, height: usize) -> Fr {
+        self.empty_roots[height]
+    }
+
+    fn node(&self, height: usize, prefix: &[bool]) -> Fr {
+        self.nodes.get(&(height, prefix.to_vec())).cloned().unwrap_or(self.empty_roots[height])
+    }
+
+    pub fn root(&self) -> Fr {
+        self.node(DEPTH, &[])
+    }
+
+    /// The leaf at `key`, which is zero if the key is absent.
+    pub fn get(&self, key: &[u8; 32]) -> Fr {
+        self.node(0, &key_bits(key))
+    }
+
+    /// Sets the leaf at `key` to `value`. Setting it to zero
+    /// removes the key.
+    pub fn insert(&mut self, key: &[u8; 32], value: Fr) {
+        let mut bits = key_bits(key);
+        let mut cur = value;
+
+        for h in 0..DEPTH + 1 {
+            if cur == self.empty_roots[h] {
+                self.nodes.remove(&(h, bits.clone()));
+            } else {
+                self.nodes.insert((h, bits.clone()), cur);
+            }
+
+            if let Some(is_right) = bits.pop() {
+                let mut sibling_bits = bits.clone();
+                sibling_bits.push(!is_right);
+                let sibling = self.node(h, &sibling_bits);
+
+                cur = if is_right {
+                    self.hash.hash(&sibling, &cur)
+                } else {
+                    self.hash.hash(&cur, &sibling)
+                };
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8; 32]) {
+        self.insert(key, Fr::zero());
+    }
+
+    /// The siblings of the leaf at `key`, from the leaf up to the
+    /// root, whether or not the key is present.
+    pub fn path(&self, key: &[u8; 32]) -> Vec<Fr> {
+        let mut bits = key_bits(key);
+
+        (0..DEPTH).map(|h| {
+            let is_right = bits.pop().unwrap();
+            let mut sibling_bits = bits.clone();
+            sibling_bits.push(!is_right);
+
+            self.node(h, &sibling_bits)
+        }).collect()
+    }
+}
+
+/// The same as `sparse_merkle_root_native`, in a circuit, where
+/// `key` is the bits of the key from the root down. Each level
+/// swaps the node and its sibling by the key's bit, with one
+/// constraint, before hashing them. The leaf can be a linear
+/// combination, such as a constant.
+pub fn sparse_merkle_root_gadget<CS>(
+    cs: &mut CS,
+    key: &[Bit],
+    leaf: &LcNum<Bls12>,
+    path: &[Num<Bls12>],
+    hash: &SparseMerkleHash
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    assert_eq!(key.len(), DEPTH);
+    assert_eq!(path.len(), DEPTH);
+
+    let (left, right) = LcNum::swap_if(cs, &key[DEPTH - 1], leaf, &path[0].clone().into())?;
+    let mut cur = hash.hash_gadget(cs, &left.into(), &right)?;
+
+    for (is_right, sibling) in key.iter().rev().zip(path.iter()).skip(1) {
+        let (left, right) = Num::swap_if(cs, is_right, &cur, sibling)?;
+
+        cur = hash.hash_gadget(cs, &left.into(), &right)?;
+    }
+
+    Ok(cur)
+}
+
+/// Computes the root of a tree in which `key` is present, with the
+/// nonzero leaf `value`. Costs one more constraint than
+/// `sparse_merkle_root_gadget`, which is unsatisfiable when
+/// `value` is zero.
+pub fn sparse_merkle_membership_gadget<CS>(
+    cs: &mut CS,
+    key: &[Bit],
+    value: &Num<Bls12>,
+    path: &[Num<Bls12>],
+    hash: &SparseMerkleHash
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    value.inverse(cs)?;
+
+    sparse_merkle_root_gadget(cs, key, &value.clone().into(), path, hash)
+}
+
+/// Computes the root of a tree in which `key` is absent. The leaf
+/// is the constant zero, so this costs no more constraints than
+/// `sparse_merkle_root_gadget`.
+pub fn sparse_merkle_non_membership_gadget<CS>(
+    cs: &mut CS,
+    key: &[Bit],
+    path: &[Num<Bls12>],
+    hash: &SparseMerkleHash
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>
+{
+    sparse_merkle_root_gadget(cs, key, &LcNum::zero(), path, hash)
+}
+
+#[test]
+fn test_sparse_merkle_tree() {
+    use rand::{Rng, thread_rng};
+    use super::generate_constant_table;
+
+    let rng = &mut thread_rng();
+    let j = JubJub::new();
+    let generators = generate_constant_table(rng, &j);
+    let mimc_params = MimcParams::default();
+
+    for hash in &[SparseMerkleHash::Pedersen { generators: &generators, j: &j }, SparseMerkleHash::Mimc(&mimc_params)] {
+        let mut tree = SparseMerkleTree::new(*hash);
+        let empty_root = tree.root();
+        assert_eq!(empty_root, tree.empty_root(DEPTH));
+
+        // Keys agree in all but their last bits, so their leaves are
+        // siblings and cousins at the bottom of the tree.
+        let prefix: [u8; 32] = rng.gen();
+        let mut keys = (0..16).map(|i| {
+            let mut key = prefix;
+            key[31] = (key[31] & 0xf0) | i;
+            key
+        }).collect::<Vec<_>>();
+        keys.push(rng.gen());
+        keys.push(rng.gen());
+        rng.shuffle(&mut keys);
+
+        let mut values = vec![];
+
+        for key in keys.iter().take(10) {
+            let value: Fr = rng.gen();
+            tree.insert(key, value);
+            values.push((*key, value));
+
+            for &(key, value) in &values {
+                assert_eq!(tree.get(&key), value);
+                assert_eq!(sparse_merkle_root_native(&key_bits(&key), &value, &tree.path(&key), hash), tree.root());
+            }
+        }
+
+        for key in keys.iter().skip(10) {
+            assert_eq!(tree.get(key), Fr::zero());
+            assert_eq!(sparse_merkle_root_native(&key_bits(key), &Fr::zero(), &tree.path(key), hash), tree.root());
+        }
+
+        // The root doesn't depend on the order of insertion.
+        let mut reversed = SparseMerkleTree::new(*hash);
+        for &(key, value) in values.iter().rev() {
+            reversed.insert(&key, value);
+        }
+        assert_eq!(reversed.root(), tree.root());
+
+        for key in keys.iter().take(10) {
+            tree.remove(key);
+        }
+
+        assert_eq!(tree.root(), empty_root);
+        assert!(tree.nodes.is_empty());
+    }
+}
+
+#[test]
+fn test_sparse_merkle_gadget() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use super::{Assignment, ConstraintCounter};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+
+    // The gadget doesn't depend on the number of rounds, and a few
+    // keep a circuit of the full depth small.
+    const ROUNDS: usize = 2;
+    let mimc_params = MimcParams::new(5, (0..ROUNDS).map(|_| rng.gen()).collect()).unwrap();
+    let hash = SparseMerkleHash::Mimc(&mimc_params);
+
+    struct MySparseMerkleCircuit<'a> {
+        key: Vec<Assignment<bool>>,
+        // The leaf for a proof of membership, or `None` for one of
+        // non-membership.
+        value: Option<Assignment<Fr>>,
+        path: Vec<Assignment<Fr>>,
+        // Witness one for anything the prover can't compute, such
+        // as the inverse of a zero leaf.
+        dishonest: bool,
+        hash: &'a SparseMerkleHash<'a>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    struct Dishonest<'a, CS: 'a>(&'a mut CS);
+
+    impl<'a, CS: ConstraintSystem<Bls12> + 'a> ConstraintSystem<Bls12> for Dishonest<'a, CS> {
+        fn alloc<F: FnOnce() -> Result<Fr, Error>>(&mut self, f: F) -> Result<Variable, Error> {
+            self.0.alloc(|| f().or(Ok(Fr::one())))
+        }
+
+        fn enforce(
+            &mut self,
+            a: LinearCombination<Bls12>,
+            b: LinearCombination<Bls12>,
+            c: LinearCombination<Bls12>
+        )
+        {
+            self.0.enforce(a, b, c)
+        }
+    }
+
+    impl<'a> Circuit<Bls12> for MySparseMerkleCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let mut key = vec![];
+            for b in self.key {
+                key.push(Bit::alloc(cs, b)?);
+            }
+
+            let mut path = vec![];
+            for sibling in self.path {
+                path.push(Num::alloc(cs, sibling)?);
+            }
+
+            let value = match self.value {
+                Some(value) => Some(Num::alloc(cs, value)?),
+                None => None
+            };
+
+            let mut counter = ConstraintCounter::new(cs);
+            let root = match value {
+                Some(value) if self.dishonest => {
+                    sparse_merkle_membership_gadget(&mut Dishonest(&mut counter), &key, &value, &path, self.hash)?
+                },
+                Some(value) => sparse_merkle_membership_gadget(&mut counter, &key, &value, &path, self.hash)?,
+                None => sparse_merkle_non_membership_gadget(&mut counter, &key, &path, self.hash)?
+            };
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(&root))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let mut tree = SparseMerkleTree::new(hash);
+    let present = (0..3).map(|_| rng.gen()).collect::<Vec<[u8; 32]>>();
+    for key in &present {
+        tree.insert(key, rng.gen());
+    }
+
+    // Differs from a present key only in its last bit.
+    let mut absent = present[0];
+    absent[31] ^= 1;
+    assert_eq!(tree.get(&absent), Fr::zero());
+
+    let circuit = |key: &[u8; 32], value: Option<Assignment<Fr>>, tree: &SparseMerkleTree| {
+        MySparseMerkleCircuit {
+            key: key_bits(key).into_iter().map(Assignment::known).collect(),
+            value: value,
+            path: tree.path(key).into_iter().map(Assignment::known).collect(),
+            dishonest: false,
+            hash: &hash,
+            num_constraints: &num_constraints
+        }
+    };
+
+    let blank = |member: bool| MySparseMerkleCircuit {
+        key: vec![Assignment::unknown(); DEPTH],
+        value: if member { Some(Assignment::unknown()) } else { None },
+        path: vec![Assignment::unknown(); DEPTH],
+        dishonest: false,
+        hash: &hash,
+        num_constraints: &num_constraints
+    };
+
+    // Each level is the swap, and a sponge of two inputs: three
+    // permutations of three constraints per round.
+    let level = 1 + 3 * ROUNDS * 3;
+
+    let (member_params, member_ivk) = input::generate_input_parameters(blank(true), rng).unwrap();
+    assert_eq!(num_constraints.get(), DEPTH * level + 1);
+
+    let (absent_params, absent_ivk) = input::generate_input_parameters(blank(false), rng).unwrap();
+    assert_eq!(num_constraints.get(), DEPTH * level);
+
+    let root = tree.root();
+
+    for key in &present {
+        let proof = create_random_proof::<Bls12, _, _, _>(
+            circuit(key, Some(Assignment::known(tree.get(key))), &tree), &member_params, rng
+        ).unwrap();
+
+        assert!(input::verify_with_inputs(&member_ivk, &proof, &[root]).unwrap());
+        assert!(!input::verify_with_inputs(&member_ivk, &proof, &[rng.gen()]).unwrap());
+    }
+
+    let proof = create_random_proof::<Bls12, _, _, _>(circuit(&absent, None, &tree), &absent_params, rng).unwrap();
+    assert!(input::verify_with_inputs(&absent_ivk, &proof, &[root]).unwrap());
+
+    // Once the key is inserted, the proof no longer verifies
+    // against the new root.
+    let mut updated = SparseMerkleTree::new(hash);
+    for key in &present {
+        updated.insert(key, tree.get(key));
+    }
+    updated.insert(&absent, Fr::one());
+    assert!(!input::verify_with_inputs(&absent_ivk, &proof, &[updated.root()]).unwrap());
+
+    // Membership can't be proven for an absent key, whose leaf is
+    // zero: an honest prover can't invert it, and any other
+    // witness fails the constraints.
+    assert!(create_random_proof::<Bls12, _, _, _>(
+        circuit(&absent, Some(Assignment::known(Fr::zero())), &tree), &member_params, rng
+    ).is_err());
+
+    let proof = create_random_proof::<Bls12, _, _, _>(MySparseMerkleCircuit {
+        dishonest: true,
+        ..circuit(&absent, Some(Assignment::known(Fr::zero())), &tree)
+    }, &member_params, rng).unwrap();
+    assert!(!input::verify_with_inputs(&member_ivk, &proof, &[root]).unwrap());
+}
+
+#[test]
+fn test_sparse_merkle_pedersen_hash() {
+    use bellman::groth16::*;
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use super::{Assignment, ConstraintCounter, generate_constant_table};
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+    let j = JubJub::new();
+    let generators = generate_constant_table(rng, &j);
+    let hash = SparseMerkleHash::Pedersen { generators: &generators, j: &j };
+
+    struct MyHashCircuit<'a> {
+        left: Assignment<Fr>,
+        right: Assignment<Fr>,
+        hash: &'a SparseMerkleHash<'a>,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyHashCircuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let left = Num::alloc(cs, self.left)?;
+            let right = Num::alloc(cs, self.right)?;
+
+            let mut counter = ConstraintCounter::new(cs);
+            let h = self.hash.hash_gadget(&mut counter, &left.into(), &right.into())?;
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().num(&h))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let (params, ivk) = input::generate_input_parameters(MyHashCircuit {
+        left: Assignment::unknown(),
+        right: Assignment::unknown(),
+        hash: &hash,
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+    // A level of `merkle::merkle_path_gadget`, without the swap.
+    assert_eq!(num_constraints.get(), 2817);
+
+    for _ in 0..3 {
+        let left: Fr = rng.gen();
+        let right: Fr = rng.gen();
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyHashCircuit {
+            left: Assignment::known(left),
+            right: Assignment::known(right),
+            hash: &hash,
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[hash.hash(&left, &right)]).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[hash.hash(&right, &left)]).unwrap());
+    }
+}
@@ -0,0 +1,90 @@
+//! The width-3 sponge and MDS matrix shared by Poseidon and
+//! Rescue.
+//!
+//! The sponge absorbs `RATE` inputs per permutation into all but
+//! the first element, which is the capacity. The capacity starts
+//! out as the number of inputs, so that zero padding can't make
+//! inputs of different lengths collide. The hash is the second
+//! element after the last permutation.
+
+use pairing::*;
+use pairing::bls12_381::{Bls12, Fr};
+use bellman::*;
+
+use super::{LcNum, Num};
+
+pub const WIDTH: usize = 3;
+pub const RATE: usize = WIDTH - 1;
+
+fn small(n: usize) -> Fr {
+    Fr::from_repr(<Fr as PrimeField>::Repr::from(n as u64)).unwrap()
+}
+
+/// The Cauchy matrix `1 / (x_i + y_j)` with `x_i = i` and
+/// `y_j = WIDTH + j`, whose entries are all defined and whose
+/// square submatrices are all invertible, so it is MDS.
+pub fn cauchy_mds() -> [[Fr; WIDTH]; WIDTH] {
+    let mut mds = [[Fr::zero(); WIDTH]; WIDTH];
+    for (i, row) in mds.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            *entry = small(i + WIDTH + j).inverse().unwrap();
+        }
+    }
+
+    mds
+}
+
+/// Hashes any number of field elements with the sponge over
+/// `permutation`.
+pub fn sponge_hash<F>(inputs: &[Fr], mut permutation: F) -> Fr
+    where F: FnMut(&mut [Fr; WIDTH])
+{
+    let mut state = [Fr::zero(); WIDTH];
+    state[0] = small(inputs.len());
+
+    let mut padded = inputs.to_vec();
+    while padded.is_empty() || padded.len() % RATE != 0 {
+        padded.push(Fr::zero());
+    }
+
+    for chunk in padded.chunks(RATE) {
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            s.add_assign(x);
+        }
+
+        permutation(&mut state);
+    }
+
+    state[1]
+}
+
+/// The same as `sponge_hash`, in a circuit, over a permutation
+/// gadget. Costs a permutation for every `RATE` inputs, and one
+/// constraint for the result.
+pub fn sponge_hash_gadget<CS, F>(
+    cs: &mut CS,
+    inputs: &[Num<Bls12>],
+    mut permutation: F
+) -> Result<Num<Bls12>, Error>
+    where CS: ConstraintSystem<Bls12>,
+          F: FnMut(&mut CS, &[LcNum<Bls12>]) -> Result<Vec<LcNum<Bls12>>, Error>
+{
+    let mut state = vec![LcNum::zero(); WIDTH];
+    state[0] = LcNum::constant::<CS>(small(inputs.len()));
+
+    // Zero padding doesn't change the state, so only the number
+    // of permutations depends on it.
+    let num_chunks = ::std::cmp::max(1, (inputs.len() + RATE - 1) / RATE);
+
+    for i in 0..num_chunks {
+        let chunk = inputs.iter().skip(i * RATE).take(RATE);
+
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            *s = s.add(&LcNum::from(x.clone()));
+        }
+
+        state = permutation(cs, &state)?;
+    }
+
+    state[1].into_num(cs)
+}
@@ -0,0 +1,523 @@
+//! An append-only Merkle tree of commitments, for producing the
+//! authentication paths `merkle::merkle_path_gadget` checks.
+//!
+//! The tree only keeps its frontier: the most recent leaf and the
+//! roots of the complete subtrees to the left of it. Leaves which
+//! haven't been appended yet are `Fr::zero()`, so the root of an
+//! empty subtree of each height is precomputed in `TreeParams`.
+//!
+//! A witness is the frontier at the time its leaf was appended,
+//! which fixes the siblings to its left, plus the roots of the
+//! subtrees to its right as they fill up with later leaves.
+
+use pairing::*;
+use pairing::bls12_381::Fr;
+
+use std::io::{self, Read, Write};
+
+use super::JubJub;
+use merkle::merkle_hash_native;
+
+/// Errors arising when appending to a tree or a witness.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreeError {
+    /// The tree already has `2^depth` leaves.
+    Full
+}
+
+/// The hash and depth of a tree, and the roots of its empty
+/// subtrees.
+pub struct TreeParams<'a> {
+    generators: &'a [(Vec<Fr>, Vec<Fr>)],
+    j: &'a JubJub,
+    empty_roots: Vec<Fr>
+}
+
+impl<'a> TreeParams<'a> {
+    pub fn new(depth: usize, generators: &'a [(Vec<Fr>, Vec<Fr>)], j: &'a JubJub) -> TreeParams<'a> {
+        // Positions have to fit in a `u64`.
+        assert!(depth < 64);
+
+        let mut params = TreeParams {
+            generators: generators,
+            j: j,
+            empty_roots: vec![Fr::zero()]
+        };
+
+        for h in 0..depth {
+            let empty = params.empty_roots[h];
+            let parent = params.hash(&empty, &empty);
+            params.empty_roots.push(parent);
+        }
+
+        params
+    }
+
+    pub fn depth(&self) -> usize {
+        self.empty_roots.len() - 1
+    }
+
+    /// The root of a subtree of height `height` with no leaves.
+    pub fn empty_root(&self, height: usize) -> Fr {
+        self.empty_roots[height]
+    }
+
+    fn hash(&self, left: &Fr, right: &Fr) -> Fr {
+        merkle_hash_native(left, right, self.generators, self.j)
+    }
+}
+
+/// The leaf at `position`, and the roots of the complete subtrees
+/// to its left, one for each set bit of `position`, lowest first.
+#[derive(Clone, Debug, PartialEq)]
+struct Frontier {
+    position: u64,
+    leaf: Fr,
+    ommers: Vec<Fr>
+}
+
+impl Frontier {
+    fn new(leaf: Fr) -> Frontier {
+        Frontier {
+            position: 0,
+            leaf: leaf,
+            ommers: vec![]
+        }
+    }
+
+    fn append(&mut self, leaf: Fr, params: &TreeParams) {
+        // The current leaf completes the subtrees of its lowest
+        // ommers, which merge with it into a single one.
+        let merged = (!self.position).trailing_zeros() as usize;
+
+        let mut carry = self.leaf;
+        for ommer in &self.ommers[..merged] {
+            carry = params.hash(ommer, &carry);
+        }
+
+        let mut ommers = vec![carry];
+        ommers.extend_from_slice(&self.ommers[merged..]);
+
+        self.position += 1;
+        self.leaf = leaf;
+        self.ommers = ommers;
+    }
+
+    /// The root of the subtree of height `height` this frontier
+    /// belongs to.
+    fn root(&self, height: usize, params: &TreeParams) -> Fr {
+        let mut ommers = self.ommers.iter();
+
+        (0..height).fold(self.leaf, |cur, h| {
+            if (self.position >> h) & 1 == 1 {
+                params.hash(ommers.next().unwrap(), &cur)
+            } else {
+                params.hash(&cur, &params.empty_roots[h])
+            }
+        })
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_u64(&mut writer, self.position)?;
+        write_fr(&mut writer, &self.leaf)?;
+
+        for ommer in &self.ommers {
+            write_fr(&mut writer, ommer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a frontier, rejecting a position outside a tree of
+    /// height `height`.
+    fn read<R: Read>(mut reader: R, height: usize) -> io::Result<Frontier> {
+        let position = read_u64(&mut reader)?;
+        if position >= 1 << height {
+            return Err(invalid_data("position out of range"));
+        }
+
+        let leaf = read_fr(&mut reader)?;
+
+        let mut ommers = vec![];
+        for _ in 0..position.count_ones() {
+            ommers.push(read_fr(&mut reader)?);
+        }
+
+        Ok(Frontier {
+            position: position,
+            leaf: leaf,
+            ommers: ommers
+        })
+    }
+}
+
+/// An append-only Merkle tree of depth `TreeParams::depth()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitmentTree {
+    frontier: Option<Frontier>
+}
+
+impl CommitmentTree {
+    pub fn new() -> CommitmentTree {
+        CommitmentTree {
+            frontier: None
+        }
+    }
+
+    /// The number of leaves appended so far.
+    pub fn size(&self) -> u64 {
+        self.frontier.as_ref().map_or(0, |f| f.position + 1)
+    }
+
+    pub fn append(&mut self, leaf: Fr, params: &TreeParams) -> Result<(), TreeError> {
+        if self.size() == 1 << params.depth() {
+            return Err(TreeError::Full);
+        }
+
+        match self.frontier {
+            Some(ref mut frontier) => frontier.append(leaf, params),
+            None => self.frontier = Some(Frontier::new(leaf))
+        }
+
+        Ok(())
+    }
+
+    pub fn root(&self, params: &TreeParams) -> Fr {
+        match self.frontier {
+            Some(ref frontier) => frontier.root(params.depth(), params),
+            None => params.empty_root(params.depth())
+        }
+    }
+
+    /// A witness for the most recently appended leaf, or `None`
+    /// if the tree is empty.
+    pub fn witness(&self) -> Option<IncrementalWitness> {
+        self.frontier.as_ref().map(|frontier| {
+            IncrementalWitness {
+                frontier: frontier.clone(),
+                filled: vec![],
+                cursor: None
+            }
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_optional_frontier(&mut writer, &self.frontier)
+    }
+
+    /// Reads a tree, rejecting one with more leaves than a tree
+    /// with these parameters can hold.
+    pub fn read<R: Read>(mut reader: R, params: &TreeParams) -> io::Result<CommitmentTree> {
+        Ok(CommitmentTree {
+            frontier: read_optional_frontier(&mut reader, params.depth())?
+        })
+    }
+}
+
+/// The authentication path of a leaf, kept up to date by
+/// appending every leaf appended to the tree after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncrementalWitness {
+    frontier: Frontier,
+    // The roots of the complete subtrees to the right of the leaf,
+    // lowest first.
+    filled: Vec<Fr>,
+    // The frontier of the next subtree to the right, while it is
+    // still incomplete.
+    cursor: Option<Frontier>
+}
+
+impl IncrementalWitness {
+    /// The position of the leaf in the tree.
+    pub fn position(&self) -> u64 {
+        self.frontier.position
+    }
+
+    pub fn leaf(&self) -> Fr {
+        self.frontier.leaf
+    }
+
+    /// The height of the next subtree to the right of the leaf
+    /// which isn't complete yet, if there is one.
+    fn next_height(&self, params: &TreeParams) -> Option<usize> {
+        let position = self.frontier.position;
+
+        (0..params.depth()).filter(|h| (position >> h) & 1 == 0).nth(self.filled.len())
+    }
+
+    pub fn append(&mut self, leaf: Fr, params: &TreeParams) -> Result<(), TreeError> {
+        let height = match self.next_height(params) {
+            Some(height) => height,
+            None => return Err(TreeError::Full)
+        };
+
+        let complete = {
+            let cursor = match self.cursor {
+                Some(ref mut cursor) => {
+                    cursor.append(leaf, params);
+                    cursor
+                },
+                None => {
+                    self.cursor = Some(Frontier::new(leaf));
+                    self.cursor.as_mut().unwrap()
+                }
+            };
+
+            if cursor.position + 1 == 1 << height {
+                Some(cursor.root(height, params))
+            } else {
+                None
+            }
+        };
+
+        if let Some(root) = complete {
+            self.filled.push(root);
+            self.cursor = None;
+        }
+
+        Ok(())
+    }
+
+    /// The siblings from the leaf up to the root, with whether the
+    /// leaf's side is the right one, as `merkle_path_native` and
+    /// `merkle_path_gadget` take them.
+    pub fn path(&self, params: &TreeParams) -> Vec<(Fr, bool)> {
+        let mut ommers = self.frontier.ommers.iter();
+        let mut filled = self.filled.iter();
+        let mut cursor = self.cursor.as_ref();
+
+        (0..params.depth()).map(|h| {
+            if (self.frontier.position >> h) & 1 == 1 {
+                (*ommers.next().unwrap(), true)
+            } else if let Some(root) = filled.next() {
+                (*root, false)
+            } else if let Some(c) = cursor.take() {
+                (c.root(h, params), false)
+            } else {
+                (params.empty_root(h), false)
+            }
+        }).collect()
+    }
+
+    pub fn root(&self, params: &TreeParams) -> Fr {
+        self.path(params).iter().fold(self.frontier.leaf, |cur, &(ref sibling, is_right)| {
+            if is_right {
+                params.hash(sibling, &cur)
+            } else {
+                params.hash(&cur, sibling)
+            }
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.frontier.write(&mut writer)?;
+
+        writer.write_all(&[self.filled.len() as u8])?;
+        for root in &self.filled {
+            write_fr(&mut writer, root)?;
+        }
+
+        write_optional_frontier(&mut writer, &self.cursor)
+    }
+
+    /// Reads a witness, rejecting one which couldn't have come
+    /// from a tree with these parameters.
+    pub fn read<R: Read>(mut reader: R, params: &TreeParams) -> io::Result<IncrementalWitness> {
+        let frontier = Frontier::read(&mut reader, params.depth())?;
+
+        // There is a filled subtree for at most each unset bit of
+        // the position.
+        let mut len = [0u8; 1];
+        reader.read_exact(&mut len)?;
+        if len[0] as usize > params.depth() - frontier.position.count_ones() as usize {
+            return Err(invalid_data("too many filled subtrees"));
+        }
+
+        let mut filled = vec![];
+        for _ in 0..len[0] {
+            filled.push(read_fr(&mut reader)?);
+        }
+
+        let mut witness = IncrementalWitness {
+            frontier: frontier,
+            filled: filled,
+            cursor: None
+        };
+
+        // The cursor is an incomplete subtree of the next height.
+        let cursor = read_optional_frontier(&mut reader, params.depth())?;
+        if let Some(ref cursor) = cursor {
+            match witness.next_height(params) {
+                Some(height) if cursor.position + 1 < 1 << height => {},
+                _ => return Err(invalid_data("invalid cursor"))
+            }
+        }
+        witness.cursor = cursor;
+
+        Ok(witness)
+    }
+}
+
+fn write_u64<W: Write>(mut writer: W, n: u64) -> io::Result<()> {
+    let bytes = (0..8).map(|i| (n >> (56 - i * 8)) as u8).collect::<Vec<_>>();
+
+    writer.write_all(&bytes)
+}
+
+fn read_u64<R: Read>(mut reader: R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(bytes.iter().fold(0, |n, &b| (n << 8) | b as u64))
+}
+
+fn write_fr<W: Write>(writer: W, fr: &Fr) -> io::Result<()> {
+    fr.into_repr().write_be(writer)
+}
+
+fn read_fr<R: Read>(reader: R) -> io::Result<Fr> {
+    let mut repr = <Fr as PrimeField>::Repr::default();
+    repr.read_be(reader)?;
+
+    Fr::from_repr(repr).map_err(|_| invalid_data("not a field element"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_optional_frontier<W: Write>(mut writer: W, frontier: &Option<Frontier>) -> io::Result<()> {
+    match *frontier {
+        Some(ref frontier) => {
+            writer.write_all(&[1])?;
+            frontier.write(writer)
+        },
+        None => writer.write_all(&[0])
+    }
+}
+
+fn read_optional_frontier<R: Read>(mut reader: R, height: usize) -> io::Result<Option<Frontier>> {
+    let mut tag = [0u8; 1];
+    reader.read_exact(&mut tag)?;
+
+    match tag[0] {
+        0 => Ok(None),
+        1 => Ok(Some(Frontier::read(reader, height)?)),
+        _ => Err(invalid_data("invalid frontier tag"))
+    }
+}
+
+#[test]
+fn test_commitment_tree() {
+    use rand::{Rng, thread_rng};
+    use merkle::merkle_path_native;
+    use super::generate_constant_table;
+
+    let rng = &mut thread_rng();
+    let j = JubJub::new();
+    let generators = generate_constant_table(rng, &j);
+
+    const DEPTH: usize = 4;
+
+    let params = TreeParams::new(DEPTH, &generators, &j);
+
+    // The root of the whole tree, computed level by level.
+    let naive_root = |leaves: &[Fr]| {
+        let mut level = leaves.to_vec();
+        level.resize(1 << DEPTH, Fr::zero());
+
+        while level.len() > 1 {
+            level = level.chunks(2).map(|pair| merkle_hash_native(&pair[0], &pair[1], &generators, &j)).collect();
+        }
+
+        level[0]
+    };
+
+    let mut tree = CommitmentTree::new();
+    assert_eq!(tree.root(&params), naive_root(&[]));
+    assert!(tree.witness().is_none());
+
+    let mut leaves = vec![];
+    let mut witnesses: Vec<IncrementalWitness> = vec![];
+
+    for i in 0..(1 << DEPTH) {
+        let leaf: Fr = rng.gen();
+
+        tree.append(leaf, &params).unwrap();
+        for witness in witnesses.iter_mut() {
+            witness.append(leaf, &params).unwrap();
+        }
+        leaves.push(leaf);
+        witnesses.push(tree.witness().unwrap());
+
+        let root = tree.root(&params);
+        assert_eq!(root, naive_root(&leaves));
+        assert_eq!(tree.size(), i + 1);
+
+        for (position, witness) in witnesses.iter().enumerate() {
+            assert_eq!(witness.position(), position as u64);
+            assert_eq!(witness.root(&params), root);
+
+            let path = witness.path(&params);
+            assert_eq!(merkle_path_native(&witness.leaf(), &path, &generators, &j), root);
+        }
+
+        // Serialization round-trips, and the read copies keep
+        // working.
+        let mut bytes = vec![];
+        tree.write(&mut bytes).unwrap();
+        tree = CommitmentTree::read(&bytes[..], &params).unwrap();
+
+        for witness in witnesses.iter_mut() {
+            let mut bytes = vec![];
+            witness.write(&mut bytes).unwrap();
+
+            let read = IncrementalWitness::read(&bytes[..], &params).unwrap();
+            assert!(read == *witness);
+            *witness = read;
+        }
+    }
+
+    assert_eq!(tree.append(Fr::zero(), &params), Err(TreeError::Full));
+    assert_eq!(witnesses[0].append(Fr::zero(), &params), Err(TreeError::Full));
+
+    // Values which aren't field elements are rejected.
+    let mut bytes = vec![];
+    tree.write(&mut bytes).unwrap();
+    for b in &mut bytes[9..41] {
+        *b = 0xff;
+    }
+    assert!(CommitmentTree::read(&bytes[..], &params).is_err());
+
+    // So are positions outside the tree, which would overflow its
+    // size.
+    for &position in &[1 << DEPTH, u64::max_value()] {
+        let mut bytes = vec![1];
+        write_u64(&mut bytes, position).unwrap();
+        for _ in 0..position.count_ones() + 1 {
+            write_fr(&mut bytes, &Fr::one()).unwrap();
+        }
+
+        assert!(CommitmentTree::read(&bytes[..], &params).is_err());
+    }
+
+    // A witness can't have more filled subtrees than its leaf has
+    // right siblings, nor a complete cursor.
+    let mut witness = witnesses[(1 << DEPTH) - 2].clone();
+    witness.cursor = None;
+    witness.filled.push(Fr::one());
+    let mut bytes = vec![];
+    witness.write(&mut bytes).unwrap();
+    assert!(IncrementalWitness::read(&bytes[..], &params).is_err());
+
+    let mut witness = witnesses[0].clone();
+    witness.filled.truncate(DEPTH - 1);
+    witness.cursor = Some(Frontier {
+        position: (1 << (DEPTH - 1)) - 1,
+        leaf: Fr::one(),
+        ommers: vec![Fr::one(); DEPTH - 1]
+    });
+    let mut bytes = vec![];
+    witness.write(&mut bytes).unwrap();
+    assert!(IncrementalWitness::read(&bytes[..], &params).is_err());
+}
@@ -0,0 +1,308 @@
+//! A gadget for 32-bit words, as used by hash functions like
+//! BLAKE2s and SHA-256.
+
+use pairing::*;
+use bellman::*;
+
+use super::{Assignment, Bit, Boolean, LcNum};
+
+/// A 32-bit word, as little-endian booleans. Rotations and shifts
+/// only rearrange the booleans, so they are free.
+#[derive(Clone)]
+pub struct UInt32 {
+    bits: Vec<Boolean>,
+    value: Assignment<u32>
+}
+
+impl UInt32 {
+    /// A constant word. Costs no constraints.
+    pub fn constant(value: u32) -> UInt32 {
+        UInt32 {
+            bits: (0..32).map(|i| Boolean::Constant((value >> i) & 1 == 1)).collect(),
+            value: Assignment::known(value)
+        }
+    }
+
+    /// Allocates a word. Costs 32 constraints.
+    pub fn alloc<E, CS>(cs: &mut CS, value: Assignment<u32>) -> Result<UInt32, Error>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let mut bits = Vec::with_capacity(32);
+        for i in 0..32 {
+            let bit = match value {
+                Assignment::Known(v) => Assignment::known((v >> i) & 1 == 1),
+                Assignment::Unknown => Assignment::unknown()
+            };
+
+            bits.push(Boolean::from_bit(Bit::alloc(cs, bit)?));
+        }
+
+        Ok(UInt32 {
+            bits: bits,
+            value: value
+        })
+    }
+
+    /// The word made of 32 little-endian booleans.
+    pub fn from_bits_le(bits: &[Boolean]) -> UInt32 {
+        assert_eq!(bits.len(), 32);
+
+        let mut value = Assignment::known(0u32);
+        for (i, b) in bits.iter().enumerate() {
+            value = match (value, b.get_value()) {
+                (Assignment::Known(v), Assignment::Known(b)) => Assignment::known(v | ((b as u32) << i)),
+                _ => Assignment::unknown()
+            };
+        }
+
+        UInt32 {
+            bits: bits.to_vec(),
+            value: value
+        }
+    }
+
+    /// The word made of 32 big-endian booleans.
+    pub fn from_bits_be(bits: &[Boolean]) -> UInt32 {
+        let mut bits = bits.to_vec();
+        bits.reverse();
+
+        UInt32::from_bits_le(&bits)
+    }
+
+    pub fn into_bits_le(self) -> Vec<Boolean> {
+        self.bits
+    }
+
+    pub fn into_bits_be(self) -> Vec<Boolean> {
+        let mut bits = self.bits;
+        bits.reverse();
+
+        bits
+    }
+
+    pub fn get_value(&self) -> Assignment<u32> {
+        self.value
+    }
+
+    /// The field element this word represents. Costs no
+    /// constraints.
+    pub fn lc<E: Engine, CS: ConstraintSystem<E>>(&self) -> LcNum<E> {
+        LcNum::from_booleans_le::<CS>(&self.bits)
+    }
+
+    /// Rotates right by `by` bits. Costs no constraints.
+    pub fn rotr(&self, by: usize) -> UInt32 {
+        let by = by % 32;
+
+        UInt32 {
+            bits: self.bits.iter().cycle().skip(by).take(32).cloned().collect(),
+            value: match self.value {
+                Assignment::Known(v) => Assignment::known(v.rotate_right(by as u32)),
+                Assignment::Unknown => Assignment::unknown()
+            }
+        }
+    }
+
+    /// Shifts right by `by` bits, filling in zeros. Costs no
+    /// constraints.
+    pub fn shr(&self, by: usize) -> UInt32 {
+        assert!(by < 32);
+
+        UInt32 {
+            bits: self.bits.iter().skip(by).cloned()
+                           .chain((0..by).map(|_| Boolean::Constant(false)))
+                           .collect(),
+            value: match self.value {
+                Assignment::Known(v) => Assignment::known(v >> by),
+                Assignment::Unknown => Assignment::unknown()
+            }
+        }
+    }
+
+    /// Computes `self ^ other`. Costs a constraint for each pair
+    /// of bits which aren't constant.
+    pub fn xor<E, CS>(&self, cs: &mut CS, other: &UInt32) -> Result<UInt32, Error>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let mut bits = Vec::with_capacity(32);
+        for (a, b) in self.bits.iter().zip(other.bits.iter()) {
+            bits.push(a.xor(b, cs)?);
+        }
+
+        Ok(UInt32 {
+            bits: bits,
+            value: match (self.value, other.value) {
+                (Assignment::Known(a), Assignment::Known(b)) => Assignment::known(a ^ b),
+                _ => Assignment::unknown()
+            }
+        })
+    }
+
+    /// Computes SHA-256's choose of `a`, `b` and `c`, bit by bit.
+    /// Costs at most a constraint per bit.
+    pub fn sha256_ch<E, CS>(cs: &mut CS, a: &UInt32, b: &UInt32, c: &UInt32) -> Result<UInt32, Error>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let mut bits = Vec::with_capacity(32);
+        for ((a, b), c) in a.bits.iter().zip(b.bits.iter()).zip(c.bits.iter()) {
+            bits.push(Boolean::sha256_ch(cs, a, b, c)?);
+        }
+
+        Ok(UInt32 {
+            bits: bits,
+            value: match (a.value, b.value, c.value) {
+                (Assignment::Known(a), Assignment::Known(b), Assignment::Known(c)) => {
+                    Assignment::known((a & b) ^ (!a & c))
+                },
+                _ => Assignment::unknown()
+            }
+        })
+    }
+
+    /// Computes SHA-256's majority of `a`, `b` and `c`, bit by
+    /// bit. Costs at most two constraints per bit.
+    pub fn sha256_maj<E, CS>(cs: &mut CS, a: &UInt32, b: &UInt32, c: &UInt32) -> Result<UInt32, Error>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        let mut bits = Vec::with_capacity(32);
+        for ((a, b), c) in a.bits.iter().zip(b.bits.iter()).zip(c.bits.iter()) {
+            bits.push(Boolean::sha256_maj(cs, a, b, c)?);
+        }
+
+        Ok(UInt32 {
+            bits: bits,
+            value: match (a.value, b.value, c.value) {
+                (Assignment::Known(a), Assignment::Known(b), Assignment::Known(c)) => {
+                    Assignment::known((a & b) ^ (a & c) ^ (b & c))
+                },
+                _ => Assignment::unknown()
+            }
+        })
+    }
+
+    /// Computes the sum of `operands` modulo `2^32`. The sum is
+    /// unpacked into 32 bits, plus enough bits for the carries,
+    /// so this costs one constraint per bit and one more.
+    pub fn addmany<E, CS>(cs: &mut CS, operands: &[UInt32]) -> Result<UInt32, Error>
+        where E: Engine, CS: ConstraintSystem<E>
+    {
+        assert!(operands.len() >= 2);
+
+        // The sum can't wrap around the modulus.
+        let max_sum = operands.len() as u64 * (u32::max_value() as u64);
+        let num_bits = 64 - max_sum.leading_zeros() as usize;
+        assert!(num_bits <= E::Fr::capacity() as usize);
+
+        let mut value = Assignment::known(0u32);
+        let mut sum = LcNum::zero();
+        let mut all_constant = true;
+
+        for op in operands {
+            value = match (value, op.value) {
+                (Assignment::Known(a), Assignment::Known(b)) => Assignment::known(a.wrapping_add(b)),
+                _ => Assignment::unknown()
+            };
+
+            sum = sum.add(&op.lc::<E, CS>());
+
+            all_constant &= op.bits.iter().all(|b| match *b {
+                Boolean::Constant(_) => true,
+                _ => false
+            });
+        }
+
+        if all_constant {
+            return Ok(UInt32::constant(*value.get()?));
+        }
+
+        let bits = sum.unpack_into(cs, num_bits)?;
+
+        Ok(UInt32 {
+            bits: bits.into_iter().take(32).map(Boolean::from_bit).collect(),
+            value: value
+        })
+    }
+}
+
+#[test]
+fn test_uint32() {
+    use bellman::groth16::*;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{Rng, thread_rng};
+    use input::{self, PublicInputs};
+    use super::ConstraintCounter;
+    use std::cell::Cell;
+
+    let rng = &mut thread_rng();
+
+    struct MyUInt32Circuit<'a> {
+        a: Assignment<u32>,
+        b: Assignment<u32>,
+        c: u32,
+        num_constraints: &'a Cell<usize>
+    }
+
+    impl<'a> Circuit<Bls12> for MyUInt32Circuit<'a> {
+        type InputMap = PublicInputs<Bls12>;
+
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<Self::InputMap, Error>
+        {
+            let a = UInt32::alloc(cs, self.a)?;
+            let b = UInt32::alloc(cs, self.b)?;
+            let c = UInt32::constant(self.c);
+
+            let mut counter = ConstraintCounter::new(cs);
+
+            // ((a ^ b) >>> 7) + (a >> 3) + c + (b ^ c)
+            let t = a.xor(&mut counter, &b)?.rotr(7);
+            let u = b.xor(&mut counter, &c)?;
+            let res = UInt32::addmany(&mut counter, &[t, a.shr(3), c.clone(), u])?;
+
+            self.num_constraints.set(counter.num_constraints);
+
+            Ok(PublicInputs::new().packed_booleans::<CS>(&res.into_bits_le()))
+        }
+    }
+
+    let num_constraints = Cell::new(0);
+
+    let c: u32 = rng.gen();
+
+    let (params, ivk) = input::generate_input_parameters(MyUInt32Circuit {
+        a: Assignment::unknown(),
+        b: Assignment::unknown(),
+        c: c,
+        num_constraints: &num_constraints
+    }, rng).unwrap();
+
+    // 32 for a ^ b, none for b ^ c, and 34 bits plus one for the
+    // sum of four operands.
+    assert_eq!(num_constraints.get(), 32 + 34 + 1);
+
+    for _ in 0..5 {
+        let a: u32 = rng.gen();
+        let b: u32 = rng.gen();
+
+        let expected = (a ^ b).rotate_right(7)
+                              .wrapping_add(a >> 3)
+                              .wrapping_add(c)
+                              .wrapping_add(b ^ c);
+
+        let proof = create_random_proof::<Bls12, _, _, _>(MyUInt32Circuit {
+            a: Assignment::known(a),
+            b: Assignment::known(b),
+            c: c,
+            num_constraints: &num_constraints
+        }, &params, rng).unwrap();
+
+        let fr = |v: u32| Fr::from_repr((v as u64).into()).unwrap();
+
+        assert!(input::verify_with_inputs(&ivk, &proof, &[fr(expected)]).unwrap());
+        assert!(!input::verify_with_inputs(&ivk, &proof, &[fr(expected ^ 1)]).unwrap());
+    }
+
+    // Constants fold without constraints.
+    let x = UInt32::constant(0xdeadbeef);
+    assert_eq!(x.rotr(8).get_value().get().unwrap(), &0xefdeadbe);
+    assert_eq!(x.shr(28).get_value().get().unwrap(), &0xd);
+}